@@ -1,9 +1,27 @@
-use std::{ops::Index, path::Path};
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Cursor, Read, Seek},
+    ops::{Index, Range},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_egui::egui::{self, RichText};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, DynamicImage, ImageFormat, RgbaImage,
+};
 
-use crate::{grid::Grid, world_pos};
+use crate::{
+    aseprite::Aseprite,
+    grid::Grid,
+    palette::{Palette, Recolor},
+    world_pos,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PixelColor([u8; 4]);
@@ -13,6 +31,56 @@ impl PixelColor {
         let p = self.0;
         PixelColor([p[0], p[1], p[2], p[3] / 2])
     }
+
+    pub fn rgba(self) -> [u8; 4] {
+        self.0
+    }
+
+    /// Parses `rrggbb` or `rrggbbaa`, with an optional leading `#`
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok();
+        Some(Self([
+            channel(0)?,
+            channel(1)?,
+            channel(2)?,
+            if hex.len() == 8 { channel(3)? } else { 255 },
+        ]))
+    }
+
+    /// The color in the Oklab color space, where distances roughly
+    /// match how different colors look
+    pub fn oklab(self) -> Vec3 {
+        let [r, g, b, _] = Color::from(self).as_linear_rgba_f32();
+        let l = (0.41222146 * r + 0.53633255 * g + 0.05144599 * b).cbrt();
+        let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+        let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+        Vec3::new(
+            0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        )
+    }
+
+    /// The opaque color closest to `lab` in the Oklab color space
+    pub fn from_oklab(lab: Vec3) -> Self {
+        let l = (lab.x + 0.39633778 * lab.y + 0.21580376 * lab.z).powi(3);
+        let m = (lab.x - 0.105561346 * lab.y - 0.06385417 * lab.z).powi(3);
+        let s = (lab.x - 0.08948418 * lab.y - 1.2914855 * lab.z).powi(3);
+        let [r, g, b, _] = Color::rgba_linear(
+            4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+            -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+            -0.0041960864 * l - 0.7034186 * m + 1.7076147 * s,
+            1.0,
+        )
+        .as_rgba_f32();
+        [r, g, b, 1.0]
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .into()
+    }
 }
 
 impl From<[u8; 4]> for PixelColor {
@@ -52,7 +120,41 @@ impl Pixel {
     }
 }
 
-#[derive(Debug, Resource)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtTransform {
+    /// Clockwise
+    Rotate90,
+    Rotate180,
+    /// Counter-clockwise
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+}
+
+impl ArtTransform {
+    pub const ALL: [Self; 6] = [
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+        Self::Transpose,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Rotate90 => "Rotate ⟳",
+            Self::Rotate180 => "Rotate 180°",
+            Self::Rotate270 => "Rotate ⟲",
+            Self::FlipHorizontal => "Flip ↔",
+            Self::FlipVertical => "Flip ↕",
+            Self::Transpose => "Transpose",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
 pub struct Art(Grid<Option<PixelColor>>);
 
 impl Index<UVec2> for Art {
@@ -64,13 +166,7 @@ impl Index<UVec2> for Art {
 }
 
 impl Art {
-    pub fn load_from_path(path: &Path) -> Result<Self, String> {
-        let image = image::io::Reader::open(path)
-            .map_err(|e| format!("Unable to open file: {e}"))?
-            .decode()
-            .map_err(|e| format!("Unable to decode image: {e}"))?
-            .into_rgba8();
-
+    pub fn from_image(image: &RgbaImage) -> Result<Self, String> {
         if image.width() < 2 || image.height() < 2 {
             return Err("Image must be at least 2x2".to_owned());
         }
@@ -80,13 +176,89 @@ impl Art {
             .map(|&image::Rgba(p)| (p[3] > 0).then_some(p.into()))
             .collect();
 
-        if data.iter().any(|p| p.is_some()) {
-            Ok(Art(Grid::from_vec(data, image.width() as usize)))
+        Self::from_grid(Grid::from_vec(data, image.width() as usize))
+    }
+
+    pub fn from_grid(grid: Grid<Option<PixelColor>>) -> Result<Self, String> {
+        if grid.width() < 2 || grid.height() < 2 {
+            Err("Image must be at least 2x2".to_owned())
+        } else if grid.rows().flatten().any(|p| p.is_some()) {
+            Ok(Art(grid))
         } else {
             Err("Image must have at least one pixel".to_owned())
         }
     }
 
+    pub fn to_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width(), self.height(), |x, y| {
+            image::Rgba(self[UVec2::new(x, y)].map_or([0; 4], PixelColor::rgba))
+        })
+    }
+
+    pub fn grid(&self) -> &Grid<Option<PixelColor>> {
+        &self.0
+    }
+
+    pub fn transformed(&self, transform: ArtTransform) -> Self {
+        Art(match transform {
+            ArtTransform::Rotate90 => self.0.rotate_90(),
+            ArtTransform::Rotate180 => self.0.rotate_180(),
+            ArtTransform::Rotate270 => self.0.rotate_270(),
+            ArtTransform::FlipHorizontal => self.0.flip_horizontal(),
+            ArtTransform::FlipVertical => self.0.flip_vertical(),
+            ArtTransform::Transpose => self.0.transpose(),
+        })
+    }
+
+    /// Splits `self` into tiles of `tile_size`, along with the index of
+    /// each tile in reading order.  Empty tiles are skipped
+    pub fn slice(&self, tile_size: UVec2) -> Vec<(usize, Art)> {
+        if tile_size.x == 0 || tile_size.y == 0 {
+            return Vec::new();
+        }
+
+        let tiles = self.size() / tile_size;
+        (0..tiles.y)
+            .flat_map(|y| (0..tiles.x).map(move |x| UVec2::new(x, y)))
+            .enumerate()
+            .filter_map(|(index, tile)| {
+                Self::from_grid(self.0.sub_grid(tile * tile_size, tile_size))
+                    .ok()
+                    .map(|art| (index, art))
+            })
+            .collect()
+    }
+
+    /// Splits `self` into tiles separated by fully transparent rows and
+    /// columns, along with the index of each tile in reading order.  Tiles
+    /// too small to be art are left out, with an error for each
+    pub fn slice_at_gutters(&self) -> (Vec<(usize, Art)>, Vec<String>) {
+        let is_empty = |x, y| self[UVec2::new(x, y)].is_none();
+
+        gutter_bands(self.height(), |y| (0..self.width()).all(|x| is_empty(x, y)))
+            .into_iter()
+            .flat_map(|rows| {
+                gutter_bands(self.width(), |x| rows.clone().all(|y| is_empty(x, y)))
+                    .into_iter()
+                    .map(move |columns| (columns, rows.clone()))
+            })
+            .enumerate()
+            .map(|(index, (columns, rows))| {
+                let min = UVec2::new(columns.start, rows.start);
+                let size = UVec2::new(columns.end, rows.end) - min;
+                Self::from_grid(self.0.sub_grid(min, size))
+                    .map(|art| (index, art))
+                    .map_err(|err| format!("Tile {} at {}, {}: {err}", index + 1, min.x, min.y))
+            })
+            .fold((Vec::new(), Vec::new()), |(mut tiles, mut errors), tile| {
+                match tile {
+                    Ok(tile) => tiles.push(tile),
+                    Err(err) => errors.push(err),
+                }
+                (tiles, errors)
+            })
+    }
+
     pub fn width(&self) -> u32 {
         self.0.width()
     }
@@ -99,19 +271,359 @@ impl Art {
         self.0.size()
     }
 
-    pub fn rows(&self) -> std::slice::Chunks<Option<PixelColor>> {
+    pub fn rows(&self) -> std::slice::Chunks<'_, Option<PixelColor>> {
         self.0.rows()
     }
 
     pub fn pixel(&self, pos: UVec2) -> Option<Pixel> {
         self[pos].map(|color| Pixel { pos, color })
     }
+
+    /// Every color used in `self`, in the order they first appear
+    pub fn colors(&self) -> Vec<PixelColor> {
+        let mut seen = HashSet::new();
+        self.rows()
+            .flatten()
+            .flatten()
+            .copied()
+            .filter(|&color| seen.insert(color))
+            .collect()
+    }
+
+    pub fn map_colors(&self, mut f: impl FnMut(PixelColor) -> PixelColor) -> Self {
+        Art(self.0.map(|color| color.map(&mut f)))
+    }
+
+    /// Returns true if the pixel at `pos` is visible in `self` and
+    /// differs from the pixel at the same position in `previous`
+    pub fn changed_from(&self, previous: &Art, pos: UVec2) -> bool {
+        self[pos].is_some() && self[pos] != previous[pos]
+    }
+
+    /// The positions that are visible in `previous` but transparent in
+    /// `self`, which an ordering of `self` never reaches
+    pub fn removed_from(&self, previous: &Art) -> Vec<UVec2> {
+        self.0
+            .positions_where(|color| color.is_none())
+            .filter(|&pos| previous[pos].is_some())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct ArtFrames {
+    frames: Vec<Art>,
+    pub current: usize,
+    /// If set, finishing the ordering of a frame moves on to the next one
+    pub animate: bool,
+    /// The layered file the frames were composed from, if any
+    pub aseprite: Option<Aseprite>,
+    /// Applied to frames as they are taken out
+    pub recolor: Option<Recolor>,
+    /// Applied to the frames so far, in order, so they can be applied
+    /// again when recomposing
+    transforms: Vec<ArtTransform>,
+}
+
+impl ArtFrames {
+    pub fn load_from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ase" | "aseprite") => {
+                let aseprite = Aseprite::load_from_path(path)?;
+                return Ok(Self {
+                    frames: Self::compose_frames(&aseprite)?,
+                    current: 0,
+                    animate: false,
+                    aseprite: Some(aseprite),
+                    recolor: None,
+                    transforms: Vec::new(),
+                });
+            }
+            Some("txt" | "pxa") => return Art::load_text_from_path(path).map(Self::from),
+            _ => {}
+        }
+
+        Self::decode(open_buffered(path)?)
+    }
+
+    /// Reads a whole image from stdin
+    pub fn load_from_stdin() -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Unable to read stdin: {e}"))?;
+        Self::decode(Cursor::new(bytes))
+    }
+
+    /// Decodes an image of any format `image` can guess, keeping every
+    /// frame of animated GIFs and APNGs
+    fn decode(input: impl BufRead + Seek) -> Result<Self, String> {
+        let reader = image::io::Reader::new(input)
+            .with_guessed_format()
+            .map_err(|e| format!("Unable to open file: {e}"))?;
+
+        let frames = match reader.format() {
+            Some(ImageFormat::Gif) => {
+                let decoder = GifDecoder::new(reader.into_inner())
+                    .map_err(|e| format!("Unable to decode image: {e}"))?;
+                Self::decode_frames(decoder)?
+            }
+            Some(ImageFormat::Png) => {
+                let decoder = PngDecoder::new(reader.into_inner())
+                    .map_err(|e| format!("Unable to decode image: {e}"))?;
+                if decoder.is_apng() {
+                    Self::decode_frames(decoder.apng())?
+                } else {
+                    let image = DynamicImage::from_decoder(decoder)
+                        .map_err(|e| format!("Unable to decode image: {e}"))?;
+                    vec![Art::from_image(&image.into_rgba8())?]
+                }
+            }
+            _ => {
+                let image = reader
+                    .decode()
+                    .map_err(|e| format!("Unable to decode image: {e}"))?;
+                vec![Art::from_image(&image.into_rgba8())?]
+            }
+        };
+
+        Ok(Self {
+            frames,
+            current: 0,
+            animate: false,
+            aseprite: None,
+            recolor: None,
+            transforms: Vec::new(),
+        })
+    }
+
+    fn compose_frames(aseprite: &Aseprite) -> Result<Vec<Art>, String> {
+        (0..aseprite.frame_count())
+            .map(|frame| {
+                Art::from_image(&aseprite.compose(frame))
+                    .map_err(|e| format!("Frame {}: {e}", frame + 1))
+            })
+            .collect()
+    }
+
+    /// Composes the frames again after the included layers of
+    /// [`Self::aseprite`] change
+    pub fn recompose(&mut self) -> Result<(), String> {
+        if let Some(aseprite) = &self.aseprite {
+            self.frames = Self::compose_frames(aseprite)?
+                .into_iter()
+                .map(|frame| {
+                    self.transforms
+                        .iter()
+                        .fold(frame, |frame, &transform| frame.transformed(transform))
+                })
+                .collect();
+            self.current = self.current.min(self.frames.len() - 1);
+        }
+        Ok(())
+    }
+
+    /// Carries the recoloring, included layers and transforms of
+    /// `previous` over to these frames, such as after reloading the file.
+    /// Layers are matched by name
+    pub fn keep_settings(&mut self, previous: &ArtFrames) -> Result<(), String> {
+        self.animate = previous.animate;
+        self.recolor = previous.recolor.clone();
+        if let (Some(aseprite), Some(previous)) = (&mut self.aseprite, &previous.aseprite) {
+            for layer in &mut aseprite.layers {
+                if let Some(old) = previous.layers.iter().find(|old| old.name == layer.name) {
+                    layer.included = old.included;
+                }
+            }
+            self.recompose()?;
+        }
+        for &transform in &previous.transforms {
+            self.transform(transform);
+        }
+        Ok(())
+    }
+
+    fn decode_frames<'a>(decoder: impl AnimationDecoder<'a>) -> Result<Vec<Art>, String> {
+        let frames = decoder
+            .into_frames()
+            .collect_frames()
+            .map_err(|e| format!("Unable to decode image: {e}"))?;
+        let frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                Art::from_image(frame.buffer()).map_err(|e| format!("Frame {}: {e}", index + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if frames.is_empty() {
+            Err("Image must have at least one frame".to_owned())
+        } else {
+            Ok(frames)
+        }
+    }
+
+    /// Transforms every frame
+    pub fn transform(&mut self, transform: ArtTransform) {
+        for frame in &mut self.frames {
+            *frame = frame.transformed(transform);
+        }
+        self.transforms.push(transform);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Every frame, recolored
+    pub fn frames(&self) -> impl Iterator<Item = Art> + '_ {
+        self.frames.iter().map(|frame| self.recolored(frame))
+    }
+
+    pub fn current(&self) -> Art {
+        self.recolored(&self.frames[self.current])
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.current + 1 < self.frames.len()
+    }
+
+    /// The frame after the current one, if there is one
+    pub fn next(&self) -> Option<Art> {
+        self.frames
+            .get(self.current + 1)
+            .map(|frame| self.recolored(frame))
+    }
+
+    /// Every color used in any frame, before recoloring
+    pub fn source_colors(&self) -> Vec<PixelColor> {
+        let mut seen = HashSet::new();
+        self.frames
+            .iter()
+            .flat_map(Art::colors)
+            .filter(|&color| seen.insert(color))
+            .collect()
+    }
+
+    /// Each color used by any frame, and how many pixels use it
+    pub fn source_color_counts(&self) -> Vec<(PixelColor, usize)> {
+        let mut counts = HashMap::new();
+        for color in self
+            .frames
+            .iter()
+            .flat_map(|frame| frame.rows().flatten().flatten())
+        {
+            *counts.entry(*color).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Recolors every frame with the `count` colors that best represent
+    /// the art
+    pub fn reduce_colors(&mut self, count: usize) {
+        let reduced = Palette::quantize(&self.source_color_counts(), count);
+        self.recolor = Some(reduced.nearest_recolor(&self.source_colors()));
+    }
+
+    fn recolored(&self, frame: &Art) -> Art {
+        match &self.recolor {
+            Some(recolor) => recolor.apply(frame),
+            None => frame.clone(),
+        }
+    }
+}
+
+impl From<Art> for ArtFrames {
+    fn from(art: Art) -> Self {
+        Self {
+            frames: vec![art],
+            current: 0,
+            animate: false,
+            aseprite: None,
+            recolor: None,
+            transforms: Vec::new(),
+        }
+    }
+}
+
+/// Splits `0..len` into the ranges between gutters
+fn gutter_bands(len: u32, is_gutter: impl Fn(u32) -> bool) -> Vec<Range<u32>> {
+    let mut bands = Vec::new();
+    let mut start = None;
+    for i in 0..len {
+        match (is_gutter(i), start) {
+            (false, None) => start = Some(i),
+            (true, Some(band_start)) => {
+                bands.push(band_start..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(band_start) = start {
+        bands.push(band_start..len);
+    }
+    bands
+}
+
+fn open_buffered(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Unable to open file: {e}"))
 }
 
 #[derive(Debug, Resource)]
 pub struct ArtName(pub String);
 
+/// The file the current art was loaded from, which is watched so the art
+/// can be reloaded when it changes
+#[derive(Debug, Resource)]
+pub struct ArtPath {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ArtPath {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            modified: modified_time(&path),
+            path,
+        }
+    }
+
+    /// Inserts the path of newly loaded art, or removes the old path if
+    /// the new art didn't come from a file
+    pub fn replace(commands: &mut Commands, path: Option<&Path>) {
+        match path {
+            Some(path) => commands.insert_resource(Self::new(path.to_owned())),
+            None => commands.remove_resource::<Self>(),
+        }
+    }
+
+    /// Returns true if the file was modified since the last call
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            false
+        } else {
+            self.modified = modified;
+            modified.is_some()
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
 impl ArtName {
+    /// Names art after the file it was loaded from
+    pub fn from_path(path: &Path) -> Self {
+        Self(path.file_name().map_or_else(
+            || "{unknown}".to_owned(),
+            |name| name.to_string_lossy().to_string(),
+        ))
+    }
+
     pub fn show(&self, context: &egui::Context) {
         egui::TopBottomPanel::bottom("art_name")
             .show_separator_line(false)
@@ -121,3 +633,24 @@ impl ArtName {
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oklab_round_trips() {
+        let channels = (0..=255).step_by(15);
+        for r in channels.clone() {
+            for g in channels.clone() {
+                for b in channels.clone() {
+                    let color = PixelColor([r, g, b, 255]);
+                    let back = PixelColor::from_oklab(color.oklab());
+                    for (a, b) in color.rgba().into_iter().zip(back.rgba()) {
+                        assert!(a.abs_diff(b) <= 1, "{color:?} came back as {back:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! Parser for the Aseprite binary format
+//! (<https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md>)
+
+use std::{fs, io::Read, path::Path};
+
+use bevy::prelude::*;
+use flate2::read::ZlibDecoder;
+use image::RgbaImage;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const HEADER_SIZE: usize = 128;
+const FRAME_HEADER_SIZE: usize = 16;
+const CHUNK_HEADER_SIZE: usize = 6;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 8;
+
+const LAYER_OPACITY_VALID: u32 = 1;
+
+/// Indexed pixels are single bytes, so palettes can't be any bigger
+const MAX_PALETTE_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorMode {
+    Rgba,
+    Grayscale,
+    Indexed { transparent_index: u8 },
+}
+
+impl ColorMode {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            ColorMode::Rgba => 4,
+            ColorMode::Grayscale => 2,
+            ColorMode::Indexed { .. } => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerType {
+    Normal,
+    Group,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    /// If this layer is drawn when composing frames
+    pub included: bool,
+    typ: LayerType,
+    is_background: bool,
+    opacity: u8,
+}
+
+impl Layer {
+    /// Group layers have no pixels of their own
+    pub fn is_group(&self) -> bool {
+        self.typ == LayerType::Group
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cel {
+    layer: usize,
+    pos: IVec2,
+    size: UVec2,
+    opacity: u8,
+    data: Vec<u8>,
+}
+
+enum CelChunk {
+    Pixels(Cel),
+    Linked { layer: usize, frame: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct Aseprite {
+    size: UVec2,
+    color_mode: ColorMode,
+    palette: Vec<[u8; 4]>,
+    pub layers: Vec<Layer>,
+    frames: Vec<Vec<Cel>>,
+}
+
+impl Aseprite {
+    pub fn load_from_path(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Unable to open file: {e}"))?;
+        Self::parse(&bytes).map_err(|e| format!("Unable to decode Aseprite file: {e}"))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut header = Reader::new(bytes);
+        header.skip(4)?;
+        if header.word()? != HEADER_MAGIC {
+            return Err("Not an Aseprite file".to_owned());
+        }
+        let frame_count = header.word()?;
+        if frame_count == 0 {
+            return Err("File has no frames".to_owned());
+        }
+        let size = UVec2::new(header.word()? as u32, header.word()? as u32);
+        let color_depth = header.word()?;
+        let flags = header.dword()?;
+        header.skip(2 + 4 + 4)?;
+        let transparent_index = header.byte()?;
+        let color_mode = match color_depth {
+            32 => ColorMode::Rgba,
+            16 => ColorMode::Grayscale,
+            8 => ColorMode::Indexed { transparent_index },
+            depth => return Err(format!("Unsupported color depth {depth}")),
+        };
+
+        let mut this = Self {
+            size,
+            color_mode,
+            palette: Vec::new(),
+            layers: Vec::new(),
+            frames: Vec::with_capacity(frame_count as usize),
+        };
+        let mut has_new_palette = false;
+        let mut group_visibility: Vec<bool> = Vec::new();
+
+        let mut reader = Reader::new(bytes);
+        reader.skip(HEADER_SIZE)?;
+        for frame_index in 0..frame_count as usize {
+            let mut frame_header = Reader::new(reader.take(FRAME_HEADER_SIZE)?);
+            let frame_size = frame_header.dword()? as usize;
+            if frame_header.word()? != FRAME_MAGIC {
+                return Err(format!("Frame {} is corrupt", frame_index + 1));
+            }
+            let old_chunk_count = frame_header.word()?;
+            frame_header.skip(2 + 2)?;
+            let new_chunk_count = frame_header.dword()?;
+            let chunk_count = if new_chunk_count == 0 {
+                old_chunk_count as u32
+            } else {
+                new_chunk_count
+            };
+
+            let mut frame = Reader::new(
+                reader.take(
+                    frame_size
+                        .checked_sub(FRAME_HEADER_SIZE)
+                        .ok_or("Frame size is too small")?,
+                )?,
+            );
+            let mut cels = Vec::new();
+            for _ in 0..chunk_count {
+                let chunk_size = frame.dword()? as usize;
+                let chunk_type = frame.word()?;
+                let mut chunk = Reader::new(
+                    frame.take(
+                        chunk_size
+                            .checked_sub(CHUNK_HEADER_SIZE)
+                            .ok_or("Chunk size is too small")?,
+                    )?,
+                );
+                match chunk_type {
+                    // Old palettes, only used if there is no new palette
+                    0x0004 | 0x0011 => {
+                        if !has_new_palette {
+                            this.read_old_palette(&mut chunk, chunk_type == 0x0011)?;
+                        }
+                    }
+                    0x2004 => {
+                        let layer_flags = chunk.word()?;
+                        let typ = match chunk.word()? {
+                            0 => LayerType::Normal,
+                            1 => LayerType::Group,
+                            2 => return Err("Tilemap layers are not supported".to_owned()),
+                            typ => return Err(format!("Unknown layer type {typ}")),
+                        };
+                        let child_level = chunk.word()? as usize;
+                        chunk.skip(2 + 2 + 2)?;
+                        let opacity = chunk.byte()?;
+                        chunk.skip(3)?;
+                        let name = chunk.string()?;
+
+                        group_visibility.truncate(child_level);
+                        let visible = layer_flags & LAYER_VISIBLE != 0
+                            && group_visibility.iter().all(|&visible| visible);
+                        if typ == LayerType::Group {
+                            group_visibility.push(visible);
+                        }
+                        this.layers.push(Layer {
+                            name,
+                            included: visible,
+                            typ,
+                            is_background: layer_flags & LAYER_BACKGROUND != 0,
+                            opacity: if flags & LAYER_OPACITY_VALID != 0 {
+                                opacity
+                            } else {
+                                u8::MAX
+                            },
+                        });
+                    }
+                    0x2005 => match this.read_cel(&mut chunk)? {
+                        Some(CelChunk::Pixels(cel)) => cels.push(cel),
+                        // Linked cels reuse a cel from an earlier frame
+                        Some(CelChunk::Linked {
+                            layer,
+                            frame: linked,
+                        }) => cels.push(
+                            this.frames
+                                .get(linked)
+                                .and_then(|cels| cels.iter().find(|cel| cel.layer == layer))
+                                .ok_or_else(|| {
+                                    format!(
+                                        "Cel in frame {} links to missing frame {}",
+                                        frame_index + 1,
+                                        linked + 1
+                                    )
+                                })?
+                                .clone(),
+                        ),
+                        None => {}
+                    },
+                    0x2019 => {
+                        has_new_palette = true;
+                        let palette_size = chunk.dword()? as usize;
+                        let first = chunk.dword()? as usize;
+                        let last = chunk.dword()? as usize;
+                        chunk.skip(8)?;
+                        if palette_size > MAX_PALETTE_SIZE {
+                            return Err(format!("Palette has {palette_size} colors"));
+                        }
+                        if first > last || last >= MAX_PALETTE_SIZE {
+                            return Err(format!("Palette changes colors {first} to {last}"));
+                        }
+                        this.palette.resize(palette_size.max(last + 1), [0; 4]);
+                        for color in &mut this.palette[first..=last] {
+                            let entry_flags = chunk.word()?;
+                            *color = [chunk.byte()?, chunk.byte()?, chunk.byte()?, chunk.byte()?];
+                            if entry_flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                        }
+                    }
+                    // Color profile, external files, mask, path, tags, user data and slices
+                    // don't affect the pixels
+                    0x2007 | 0x2008 | 0x2016 | 0x2017 | 0x2018 | 0x2020 | 0x2022 => {}
+                    // Cel extra chunks only hold precise bounds for reference layers
+                    0x2006 => {}
+                    0x2023 => return Err("Tilesets are not supported".to_owned()),
+                    typ => return Err(format!("Unsupported chunk type {typ:#06x}")),
+                }
+            }
+
+            this.frames.push(cels);
+        }
+
+        Ok(this)
+    }
+
+    fn read_old_palette(&mut self, chunk: &mut Reader, six_bit: bool) -> Result<(), String> {
+        let mut index = 0;
+        for _ in 0..chunk.word()? {
+            index += chunk.byte()? as usize;
+            let count = match chunk.byte()? {
+                0 => 256,
+                count => count as usize,
+            };
+            if self.palette.len() < index + count {
+                self.palette.resize(index + count, [0; 4]);
+            }
+            for color in &mut self.palette[index..index + count] {
+                let mut channel = || -> Result<u8, String> {
+                    let value = chunk.byte()?;
+                    Ok(if six_bit {
+                        (value << 2) | (value >> 4)
+                    } else {
+                        value
+                    })
+                };
+                *color = [channel()?, channel()?, channel()?, u8::MAX];
+            }
+            index += count;
+        }
+        Ok(())
+    }
+
+    fn read_cel(&self, chunk: &mut Reader) -> Result<Option<CelChunk>, String> {
+        let layer = chunk.word()? as usize;
+        let pos = IVec2::new(chunk.short()? as i32, chunk.short()? as i32);
+        let opacity = chunk.byte()?;
+        let cel_type = chunk.word()?;
+        chunk.skip(2 + 5)?;
+
+        if !matches!(self.layers.get(layer), Some(layer) if !layer.is_group()) {
+            // Aseprite never writes cels for groups
+            return Ok(None);
+        }
+
+        let (size, data) = match cel_type {
+            0 | 2 => {
+                let size = UVec2::new(chunk.word()? as u32, chunk.word()? as u32);
+                let len = size.x as usize * size.y as usize * self.color_mode.bytes_per_pixel();
+                let data = if cel_type == 0 {
+                    chunk.take(len)?.to_vec()
+                } else {
+                    // The size comes from the file, so it isn't trusted for
+                    // the capacity
+                    let mut data = Vec::new();
+                    ZlibDecoder::new(chunk.rest())
+                        .take(len as u64)
+                        .read_to_end(&mut data)
+                        .map_err(|e| format!("Unable to decompress cel: {e}"))?;
+                    data
+                };
+                if data.len() != len {
+                    return Err("Cel has too few pixels".to_owned());
+                }
+                (size, data)
+            }
+            1 => {
+                return Ok(Some(CelChunk::Linked {
+                    layer,
+                    frame: chunk.word()? as usize,
+                }))
+            }
+            3 => return Err("Tilemap cels are not supported".to_owned()),
+            typ => return Err(format!("Unknown cel type {typ}")),
+        };
+
+        Ok(Some(CelChunk::Pixels(Cel {
+            layer,
+            pos,
+            size,
+            opacity,
+            data,
+        })))
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Draws the included layers of `frame` over a transparent background
+    pub fn compose(&self, frame: usize) -> RgbaImage {
+        let mut image = RgbaImage::new(self.size.x, self.size.y);
+        let mut cels: Vec<_> = self.frames[frame]
+            .iter()
+            .filter(|cel| self.layers[cel.layer].included)
+            .collect();
+        cels.sort_by_key(|cel| cel.layer);
+
+        let bytes_per_pixel = self.color_mode.bytes_per_pixel();
+        for cel in cels {
+            let layer = &self.layers[cel.layer];
+            let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+            for (pixel, cel_pos) in cel.data.chunks_exact(bytes_per_pixel).zip(0..) {
+                let pos =
+                    cel.pos + IVec2::new(cel_pos % cel.size.x as i32, cel_pos / cel.size.x as i32);
+                if pos.x < 0
+                    || pos.y < 0
+                    || pos.x >= self.size.x as i32
+                    || pos.y >= self.size.y as i32
+                {
+                    continue;
+                }
+
+                let [r, g, b, a] = self.color(pixel, layer.is_background);
+                let a = a as u32 * opacity / 255;
+                let image::Rgba(dst) = image.get_pixel_mut(pos.x as u32, pos.y as u32);
+                *dst = blend_normal(*dst, [r, g, b], a);
+            }
+        }
+        image
+    }
+
+    fn color(&self, pixel: &[u8], is_background: bool) -> [u8; 4] {
+        match self.color_mode {
+            ColorMode::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            ColorMode::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            ColorMode::Indexed { transparent_index } => {
+                if pixel[0] == transparent_index && !is_background {
+                    [0; 4]
+                } else {
+                    self.palette
+                        .get(pixel[0] as usize)
+                        .copied()
+                        .unwrap_or([0; 4])
+                }
+            }
+        }
+    }
+}
+
+/// Draws `src` with `alpha` over `dst`
+fn blend_normal(dst: [u8; 4], src: [u8; 3], alpha: u32) -> [u8; 4] {
+    let dst_alpha = dst[3] as u32 * (255 - alpha) / 255;
+    let out_alpha = alpha + dst_alpha;
+    if out_alpha == 0 {
+        return [0; 4];
+    }
+    let channel =
+        |i: usize| ((src[i] as u32 * alpha + dst[i] as u32 * dst_alpha) / out_alpha) as u8;
+    [channel(0), channel(1), channel(2), out_alpha as u8]
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.bytes.len() {
+            return Err("Unexpected end of file".to_owned());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), String> {
+        self.take(len).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn short(&mut self) -> Result<i16, String> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn dword(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.word()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(typ: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = ((data.len() + CHUNK_HEADER_SIZE) as u32)
+            .to_le_bytes()
+            .to_vec();
+        bytes.extend(typ.to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    /// A file that's `size` pixels, with one frame holding `chunks`
+    fn file(size: UVec2, color_depth: u16, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut header = vec![0; HEADER_SIZE];
+        header[4..6].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+        header[6..8].copy_from_slice(&1u16.to_le_bytes());
+        header[8..10].copy_from_slice(&(size.x as u16).to_le_bytes());
+        header[10..12].copy_from_slice(&(size.y as u16).to_le_bytes());
+        header[12..14].copy_from_slice(&color_depth.to_le_bytes());
+        header[14..18].copy_from_slice(&LAYER_OPACITY_VALID.to_le_bytes());
+
+        let data = chunks.concat();
+        let mut frame = ((data.len() + FRAME_HEADER_SIZE) as u32)
+            .to_le_bytes()
+            .to_vec();
+        frame.extend(FRAME_MAGIC.to_le_bytes());
+        frame.extend(0u16.to_le_bytes());
+        frame.extend([0; 4]);
+        frame.extend((chunks.len() as u32).to_le_bytes());
+        frame.extend(data);
+
+        let mut bytes = header;
+        bytes.extend(frame);
+        let len = bytes.len() as u32;
+        bytes[0..4].copy_from_slice(&len.to_le_bytes());
+        bytes
+    }
+
+    fn layer(name: &str) -> Vec<u8> {
+        let mut data = LAYER_VISIBLE.to_le_bytes().to_vec();
+        data.extend([0; 2 + 2 + 2 + 2 + 2]);
+        data.push(u8::MAX);
+        data.extend([0; 3]);
+        data.extend((name.len() as u16).to_le_bytes());
+        data.extend(name.as_bytes());
+        chunk(0x2004, &data)
+    }
+
+    fn raw_cel(size: UVec2, pixels: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; 2 + 2 + 2];
+        data.push(u8::MAX);
+        data.extend([0; 2 + 2 + 5]);
+        data.extend((size.x as u16).to_le_bytes());
+        data.extend((size.y as u16).to_le_bytes());
+        data.extend(pixels);
+        chunk(0x2005, &data)
+    }
+
+    fn palette(size: u32, first: u32, colors: &[[u8; 4]]) -> Vec<u8> {
+        let last = first + colors.len() as u32 - 1;
+        let mut data = Vec::new();
+        for value in [size, first, last] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend([0; 8]);
+        for color in colors {
+            data.extend(0u16.to_le_bytes());
+            data.extend(color);
+        }
+        chunk(0x2019, &data)
+    }
+
+    #[test]
+    fn parses_minimal_rgba_file() {
+        let red = [255, 0, 0, 255];
+        let pixels = [red, [0; 4], [0; 4], red].concat();
+        let bytes = file(
+            UVec2::new(2, 2),
+            32,
+            &[layer("Layer 1"), raw_cel(UVec2::new(2, 2), &pixels)],
+        );
+
+        let aseprite = Aseprite::parse(&bytes).unwrap();
+        assert_eq!(aseprite.frame_count(), 1);
+        assert_eq!(aseprite.layers.len(), 1);
+        assert_eq!(aseprite.layers[0].name, "Layer 1");
+        let image = aseprite.compose(0);
+        assert_eq!(image.get_pixel(0, 0).0, red);
+        assert_eq!(image.get_pixel(1, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(1, 1).0, red);
+    }
+
+    #[test]
+    fn parses_indexed_file_with_palette() {
+        let green = [0, 255, 0, 255];
+        let bytes = file(
+            UVec2::new(2, 1),
+            8,
+            &[
+                palette(2, 0, &[[0; 4], green]),
+                layer("Layer 1"),
+                raw_cel(UVec2::new(2, 1), &[1, 0]),
+            ],
+        );
+
+        let image = Aseprite::parse(&bytes).unwrap().compose(0);
+        assert_eq!(image.get_pixel(0, 0).0, green);
+        assert_eq!(image.get_pixel(1, 0).0, [0; 4]);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = file(UVec2::new(2, 2), 32, &[]);
+        bytes[4] = 0;
+        assert!(Aseprite::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = file(
+            UVec2::new(2, 2),
+            32,
+            &[layer("Layer 1"), raw_cel(UVec2::new(2, 2), &[0; 16])],
+        );
+        for len in [0, 10, HEADER_SIZE, HEADER_SIZE + 8, bytes.len() - 1] {
+            assert!(Aseprite::parse(&bytes[..len]).is_err(), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn rejects_cel_with_too_few_pixels() {
+        let bytes = file(
+            UVec2::new(2, 2),
+            32,
+            &[layer("Layer 1"), raw_cel(UVec2::new(2, 2), &[0; 15])],
+        );
+        assert!(Aseprite::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_chunk_smaller_than_its_header() {
+        let mut bad_chunk = chunk(0x2004, &[]);
+        bad_chunk[0..4].copy_from_slice(&2u32.to_le_bytes());
+        let bytes = file(UVec2::new(2, 2), 32, &[bad_chunk]);
+        assert!(Aseprite::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_palette_ranges() {
+        let mut backwards = palette(4, 0, &[[0; 4]]);
+        backwards[10..14].copy_from_slice(&3u32.to_le_bytes());
+        for bad_palette in [
+            backwards,
+            palette(256, 255, &[[0; 4], [0; 4]]),
+            palette(u32::MAX, 0, &[[0; 4]]),
+        ] {
+            let bytes = file(UVec2::new(2, 2), 8, &[bad_palette]);
+            assert!(Aseprite::parse(&bytes).is_err());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! A grid of bools packed into words, split into chunks that are shared
+//! between clones until they're written to
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::grid;
+
+/// 64 words, so each chunk covers 4096 positions
+const CHUNK_WORDS: usize = 64;
+
+const CHUNK_BITS: usize = CHUNK_WORDS * u64::BITS as usize;
+
+type Chunk = [u64; CHUNK_WORDS];
+
+/// Cloning only copies a pointer per chunk, so snapshots can be handed to
+/// tasks cheaply.  Setting a bit copies its chunk if a snapshot shares it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    size: UVec2,
+    chunks: Vec<Arc<Chunk>>,
+}
+
+impl BitGrid {
+    /// A grid with every bit unset
+    pub fn new(size: UVec2) -> Self {
+        let bits = size.x as usize * size.y as usize;
+        let empty = Arc::new([0; CHUNK_WORDS]);
+        Self {
+            size,
+            chunks: vec![empty; bits.div_ceil(CHUNK_BITS)],
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn contains(&self, pos: UVec2) -> bool {
+        pos.cmplt(self.size).all()
+    }
+
+    /// The chunk, word and bit of `pos`
+    fn locate(&self, pos: UVec2) -> (usize, usize, u64) {
+        assert!(self.contains(pos), "{pos} is outside {}", self.size);
+        let index = pos.y as usize * self.size.x as usize + pos.x as usize;
+        let word = index % CHUNK_BITS / u64::BITS as usize;
+        (index / CHUNK_BITS, word, 1 << (index % u64::BITS as usize))
+    }
+
+    /// False outside the grid
+    pub fn get(&self, pos: UVec2) -> bool {
+        if !self.contains(pos) {
+            return false;
+        }
+        let (chunk, word, bit) = self.locate(pos);
+        self.chunks[chunk][word] & bit != 0
+    }
+
+    pub fn set(&mut self, pos: UVec2, value: bool) {
+        let (chunk, word, bit) = self.locate(pos);
+        if (self.chunks[chunk][word] & bit != 0) != value {
+            let word = &mut Arc::make_mut(&mut self.chunks[chunk])[word];
+            *word ^= bit;
+        }
+    }
+
+    /// `pos + offset`, if it's inside the grid
+    pub fn offset(&self, pos: UVec2, offset: IVec2) -> Option<UVec2> {
+        grid::offset_within(self.size, pos, offset)
+    }
+
+    /// False outside the grid
+    pub fn get_offset(&self, pos: UVec2, offset: IVec2) -> bool {
+        self.offset(pos, offset).is_some_and(|pos| self.get(pos))
+    }
+
+    /// How many bits are set
+    pub fn count_ones(&self) -> usize {
+        self.words().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn words(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chunks.iter().flat_map(|chunk| chunk.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    /// Big enough to need more than one chunk
+    const SIZE: UVec2 = UVec2::new(100, 50);
+
+    fn assert_matches(bits: &BitGrid, bools: &Grid<bool>) {
+        for (pos, &value) in bools.iter_with_pos() {
+            assert_eq!(bits.get(pos), value, "at {pos}");
+        }
+        assert_eq!(
+            bits.count_ones(),
+            bools.positions_where(|&value| value).count()
+        );
+    }
+
+    #[test]
+    fn matches_grid_of_bools() {
+        let rng = fastrand::Rng::with_seed(7);
+        let mut bits = BitGrid::new(SIZE);
+        let mut bools = Grid::new(SIZE);
+        for _ in 0..3000 {
+            let pos = UVec2::new(rng.u32(..SIZE.x), rng.u32(..SIZE.y));
+            let value = rng.bool();
+            bits.set(pos, value);
+            bools[pos] = value;
+        }
+        assert_matches(&bits, &bools);
+    }
+
+    #[test]
+    fn outside_is_unset() {
+        let mut bits = BitGrid::new(SIZE);
+        bits.set(SIZE - 1, true);
+        assert!(!bits.get(SIZE));
+        assert!(!bits.get(UVec2::new(SIZE.x, 0)));
+        assert!(bits.get_offset(SIZE - 2, IVec2::ONE));
+        assert!(!bits.get_offset(SIZE - 1, IVec2::ONE));
+        assert!(!bits.get_offset(UVec2::ZERO, IVec2::NEG_ONE));
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        let mut bits = BitGrid::new(SIZE);
+        bits.set(UVec2::ZERO, true);
+        let snapshot = bits.clone();
+        bits.set(UVec2::ZERO, false);
+        bits.set(SIZE - 1, true);
+
+        assert!(snapshot.get(UVec2::ZERO));
+        assert!(!snapshot.get(SIZE - 1));
+        assert_eq!(snapshot.count_ones(), 1);
+        assert!(!bits.get(UVec2::ZERO));
+        assert_eq!(bits.count_ones(), 1);
+    }
+}
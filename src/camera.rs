@@ -1,5 +1,6 @@
 use bevy::{
     core_pipeline::tonemapping::Tonemapping,
+    input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
     render::{
         camera::{camera_system, CameraProjection, CameraRenderGraph},
@@ -8,14 +9,33 @@ use bevy::{
     },
 };
 
+use bevy_egui::EguiContext;
+
+use crate::{
+    art::Art,
+    input::{FIT_KEYS, PAN_BUTTONS, ZOOM_IN_KEYS, ZOOM_OUT_KEYS},
+};
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 64.0;
+
+/// How much one line of scrolling zooms
+const WHEEL_ZOOM: f32 = 1.2;
+/// How many pixels of smooth scrolling make a line
+const PIXELS_PER_LINE: f32 = 50.0;
+/// How much holding a zoom key zooms each second
+const KEY_ZOOM: f32 = 3.0;
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(startup_system).add_system_to_stage(
-            CoreStage::PostUpdate,
-            camera_system::<AreaTrackingProjection>,
-        );
+        app.add_startup_system(startup_system)
+            .add_system(pan_zoom_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                camera_system::<AreaTrackingProjection>,
+            );
     }
 }
 
@@ -25,10 +45,85 @@ pub struct AreaTrackingProjection {
     far: f32,
     pub tracked_area: Rect,
     screen_aspect: f32,
+    /// How far the user has zoomed into the tracked area
+    zoom: f32,
+    /// How far the user has moved the view from the middle of the tracked
+    /// area, in world units
+    pan: Vec2,
 }
 
-fn startup_system(mut commands: Commands) {
-    let projection = AreaTrackingProjection::default();
+impl AreaTrackingProjection {
+    /// Tracks all of `art`, with a border of one pixel.  If that's a
+    /// different area, the zoom and pan were for other art, so the view is
+    /// fitted again
+    pub fn track_art(&mut self, art: &Art) {
+        let size = art.size().as_vec2();
+        let area = Rect {
+            min: Vec2::new(-1.0, -size.y),
+            max: Vec2::new(size.x, 1.0),
+        };
+        if area != self.tracked_area {
+            self.tracked_area = area;
+            self.fit();
+        }
+    }
+
+    /// Undoes the user's zooming and panning, so the whole tracked area
+    /// fits the screen again
+    pub fn fit(&mut self) {
+        self.zoom = 1.0;
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Zooms by `factor`, keeping `focus` at the same place on screen
+    pub fn zoom_at(&mut self, factor: f32, focus: Vec2) {
+        let center = self.visible_area().center();
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan += (focus - center) * (1.0 - self.zoom / zoom);
+        self.zoom = zoom;
+    }
+
+    /// The part of the world on screen: the tracked area grown to the
+    /// screen's aspect ratio, then zoomed and panned by the user
+    pub fn visible_area(&self) -> Rect {
+        let tracked_aspect = aspect(self.tracked_area.size());
+        let fitted = if tracked_aspect > self.screen_aspect {
+            // Collides with top/bottom of screen
+            let midpoint = self.tracked_area.center().x;
+            let half_width = self.tracked_area.size().y * (1.0 / self.screen_aspect) * 0.5;
+            Rect::new(
+                midpoint - half_width,
+                self.tracked_area.min.y,
+                midpoint + half_width,
+                self.tracked_area.max.y,
+            )
+        } else {
+            // Collides with left/right of screen
+            let midpoint = self.tracked_area.center().y;
+            let half_height = self.tracked_area.size().x * (self.screen_aspect) * 0.5;
+            Rect::new(
+                self.tracked_area.min.x,
+                midpoint - half_height,
+                self.tracked_area.max.x,
+                midpoint + half_height,
+            )
+        };
+        Rect::from_center_half_size(fitted.center() + self.pan, fitted.half_size() / self.zoom)
+    }
+
+    /// The world position under `screen_pos`, measured from the bottom left
+    /// of a window that's `window_size`
+    pub fn screen_to_world(&self, screen_pos: Vec2, window_size: Vec2) -> Vec2 {
+        let area = self.visible_area();
+        area.min + screen_pos / window_size * area.size()
+    }
+}
+
+fn startup_system(mut commands: Commands, art: Option<Res<Art>>) {
+    let mut projection = AreaTrackingProjection::default();
+    if let Some(art) = art {
+        projection.track_art(&art);
+    }
     let transform = Transform::from_xyz(0.0, 0.0, projection.far - 100.1);
     let view_projection = projection.get_projection_matrix() * transform.compute_matrix().inverse();
     let frustum = Frustum::from_view_projection(
@@ -53,32 +148,10 @@ fn startup_system(mut commands: Commands) {
 
 impl CameraProjection for AreaTrackingProjection {
     fn get_projection_matrix(&self) -> Mat4 {
-        let tracked_aspect = aspect(self.tracked_area.size());
-        if tracked_aspect > self.screen_aspect {
-            // Collides with top/bottom of screen
-            let midpoint = self.tracked_area.center().x;
-            let half_width = self.tracked_area.size().y * (1.0 / self.screen_aspect) * 0.5;
-            Mat4::orthographic_rh(
-                midpoint - half_width,
-                midpoint + half_width,
-                self.tracked_area.min.y,
-                self.tracked_area.max.y,
-                self.near,
-                self.far,
-            )
-        } else {
-            // Collides with left/right of screen
-            let midpoint = self.tracked_area.center().y;
-            let half_height = self.tracked_area.size().x * (self.screen_aspect) * 0.5;
-            Mat4::orthographic_rh(
-                self.tracked_area.min.x,
-                self.tracked_area.max.x,
-                midpoint - half_height,
-                midpoint + half_height,
-                self.near,
-                self.far,
-            )
-        }
+        let area = self.visible_area();
+        Mat4::orthographic_rh(
+            area.min.x, area.max.x, area.min.y, area.max.y, self.near, self.far,
+        )
     }
 
     fn update(&mut self, width: f32, height: f32) {
@@ -97,6 +170,70 @@ impl Default for AreaTrackingProjection {
             far: 1000.0,
             tracked_area: Rect::new(0.0, 0.0, 0.0, 0.0),
             screen_aspect: 1.0,
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+/// Zooms with the mouse wheel and the zoom keys, and pans by dragging,
+/// unless egui is using the input
+#[allow(clippy::too_many_arguments)]
+fn pan_zoom_system(
+    mut egui_context: ResMut<EguiContext>,
+    windows: Res<Windows>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    time: Res<Time>,
+    mut projection_query: Query<&mut AreaTrackingProjection>,
+    mut last_cursor: Local<Option<Vec2>>,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    let cursor = window.cursor_position();
+    let ctx = egui_context.ctx_mut();
+    let over_egui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+    let typing = ctx.wants_keyboard_input();
+    let mut projection = projection_query.single_mut();
+
+    let lines: f32 = wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if let (false, Some(cursor)) = (lines == 0.0 || over_egui, cursor) {
+        let focus = projection.screen_to_world(cursor, window_size);
+        projection.zoom_at(WHEEL_ZOOM.powf(lines), focus);
+    }
+
+    if !typing {
+        let direction =
+            keys.any_pressed(ZOOM_IN_KEYS) as i32 - keys.any_pressed(ZOOM_OUT_KEYS) as i32;
+        if direction != 0 {
+            let center = projection.visible_area().center();
+            let factor = KEY_ZOOM.powf(direction as f32 * time.delta_seconds());
+            projection.zoom_at(factor, center);
+        }
+        if keys.any_just_pressed(FIT_KEYS) {
+            projection.fit();
+        }
+    }
+
+    if buttons.any_just_pressed(PAN_BUTTONS) && !over_egui {
+        *last_cursor = cursor;
+    } else if !buttons.any_pressed(PAN_BUTTONS) {
+        *last_cursor = None;
+    }
+    if let (Some(last), Some(cursor)) = (*last_cursor, cursor) {
+        if last != cursor {
+            let area = projection.visible_area();
+            projection.pan -= (cursor - last) / window_size * area.size();
+            *last_cursor = Some(cursor);
         }
     }
 }
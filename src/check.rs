@@ -0,0 +1,254 @@
+//! The `check` command, which loads art and checks every ordering of it
+//! without opening a window
+
+use bevy::prelude::*;
+
+use crate::{
+    art::ArtFrames,
+    grid::{Connectivity, Region},
+    ordering::{OrderingStats, OrderingType, Orderings},
+    playlist,
+};
+
+struct Report {
+    size: UVec2,
+    frames: usize,
+    colors: usize,
+    /// Areas of the first frame touching along their sides, which the cart
+    /// has to zip between
+    islands: Vec<Region>,
+    /// Pixels of the first frame not touching any other, even at a corner
+    specks: usize,
+    orderings: Vec<(OrderingType, Result<OrderingStats, String>)>,
+}
+
+impl Report {
+    fn new(frames: &ArtFrames) -> Self {
+        let art: Vec<_> = frames.frames().collect();
+        let orderings = OrderingType::ALL
+            .into_iter()
+            .map(|typ| {
+                // Adds up the stats of every frame
+                let mut total = OrderingStats {
+                    pixels: 0,
+                    jumps: 0,
+                    longest_step: 0,
+                };
+                for (index, frame) in art.iter().enumerate() {
+                    let stats = Orderings::default()
+                        .get_or_generate(typ, frame)
+                        .check(frame)
+                        .map_err(|e| format!("Frame {}: {e}", index + 1));
+                    match stats {
+                        Ok(stats) => {
+                            total.pixels += stats.pixels;
+                            total.jumps += stats.jumps;
+                            total.longest_step = total.longest_step.max(stats.longest_step);
+                        }
+                        Err(err) => return (typ, Err(err)),
+                    }
+                }
+                (typ, Ok(total))
+            })
+            .collect();
+
+        let grid = art[0].grid();
+        Self {
+            size: art[0].size(),
+            frames: art.len(),
+            colors: frames.source_colors().len(),
+            islands: grid.components(Connectivity::Four, Option::is_some).regions,
+            specks: grid
+                .components(Connectivity::Eight, Option::is_some)
+                .regions
+                .iter()
+                .filter(|region| region.pixels == 1)
+                .count(),
+            orderings,
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.orderings.iter().all(|(_, stats)| stats.is_ok())
+    }
+}
+
+/// Checks all of `args`, printing a report.  Returns false if any art
+/// failed to load or has an invalid ordering
+pub fn run(args: Vec<String>, json: bool) -> bool {
+    let reports: Vec<_> = playlist::load_all(args)
+        .into_iter()
+        .map(|(name, entry)| (name, entry.map(|entry| Report::new(&entry.frames))))
+        .collect();
+
+    if json {
+        print_json(&reports);
+    } else {
+        print_table(&reports);
+    }
+    reports
+        .iter()
+        .all(|(_, report)| report.as_ref().is_ok_and(Report::is_ok))
+}
+
+fn print_table(reports: &[(String, Result<Report, String>)]) {
+    let mut rows = vec![[
+        "FILE", "SIZE", "FRAMES", "COLORS", "ORDERING", "PIXELS", "JUMPS", "LONGEST", "RESULT",
+    ]
+    .map(str::to_owned)];
+    for (name, report) in reports {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                let mut row = [(); 9].map(|_| "-".to_owned());
+                row[0] = name.clone();
+                row[8] = err.clone();
+                rows.push(row);
+                continue;
+            }
+        };
+        for (typ, stats) in &report.orderings {
+            let mut row = [
+                name.clone(),
+                format!("{}x{}", report.size.x, report.size.y),
+                report.frames.to_string(),
+                report.colors.to_string(),
+                typ.name().to_owned(),
+            ]
+            .into_iter()
+            .chain(match stats {
+                Ok(stats) => [
+                    stats.pixels.to_string(),
+                    stats.jumps.to_string(),
+                    stats.longest_step.to_string(),
+                    "ok".to_owned(),
+                ],
+                Err(err) => ["-".to_owned(), "-".to_owned(), "-".to_owned(), err.clone()],
+            });
+            rows.push([(); 9].map(|_| row.next().unwrap()));
+        }
+    }
+
+    let mut widths = [0; 9];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+
+    for (name, report) in reports {
+        if let Ok(report) = report {
+            if report.specks > 0 {
+                eprintln!("warning: {name} has {} disconnected specks", report.specks);
+            }
+            if report.islands.len() > 1 {
+                eprintln!(
+                    "warning: {name} has {} islands the cart has to zip between",
+                    report.islands.len()
+                );
+            }
+        }
+    }
+}
+
+fn print_json(reports: &[(String, Result<Report, String>)]) {
+    let entries: Vec<_> = reports
+        .iter()
+        .map(|(name, report)| match report {
+            Ok(report) => {
+                let orderings: Vec<_> = report
+                    .orderings
+                    .iter()
+                    .map(|(typ, stats)| match stats {
+                        Ok(stats) => format!(
+                            r#"{{"ordering":{},"pixels":{},"jumps":{},"longest_step":{}}}"#,
+                            json_string(typ.name()),
+                            stats.pixels,
+                            stats.jumps,
+                            stats.longest_step,
+                        ),
+                        Err(err) => format!(
+                            r#"{{"ordering":{},"error":{}}}"#,
+                            json_string(typ.name()),
+                            json_string(err),
+                        ),
+                    })
+                    .collect();
+                let islands: Vec<_> = report
+                    .islands
+                    .iter()
+                    .map(|island| {
+                        format!(
+                            r#"{{"x":{},"y":{},"width":{},"height":{},"pixels":{}}}"#,
+                            island.min.x,
+                            island.min.y,
+                            island.size().x,
+                            island.size().y,
+                            island.pixels,
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{"file":{},"width":{},"height":{},"frames":{},"colors":{},"islands":[{}],"specks":{},"orderings":[{}]}}"#,
+                    json_string(name),
+                    report.size.x,
+                    report.size.y,
+                    report.frames,
+                    report.colors,
+                    islands.join(","),
+                    report.specks,
+                    orderings.join(","),
+                )
+            }
+            Err(err) => format!(
+                r#"{{"file":{},"error":{}}}"#,
+                json_string(name),
+                json_string(err)
+            ),
+        })
+        .collect();
+    println!("[{}]", entries.join(",\n "));
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("art.png"), r#""art.png""#);
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"C:\art\cat.png"), r#""C:\\art\\cat.png""#);
+        assert_eq!(json_string("a\nb\tc\u{1}"), r#""a\nb\u0009c\u0001""#);
+        assert_eq!(json_string("\u{7f}\u{85}"), r#""\u007f\u0085""#);
+    }
+
+    #[test]
+    fn json_string_keeps_unicode() {
+        assert_eq!(json_string("café 猫 🎨"), "\"café 猫 🎨\"");
+    }
+}
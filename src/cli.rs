@@ -0,0 +1,380 @@
+use std::{env, process, str::FromStr};
+
+use bevy::prelude::Color;
+
+use crate::{
+    art::PixelColor, export::ExportSettings, game::GameType, menu::Selection,
+    ordering::OrderingType, timelapse::Timelapse, GameState,
+};
+
+const USAGE: &str = "\
+Usage: pixel_art_games [OPTIONS] [ART]...
+       pixel_art_games check [--json] <ART>...
+       pixel_art_games timelapse [OPTIONS] <ART>
+
+Loads each ART file, or every file in each ART directory, as a playlist.
+`-` reads an image from stdin.
+
+`check` loads the art without opening a window, checks every ordering of
+it, and prints a report.  It fails if anything is wrong.
+
+`timelapse` saves the ordering drawing the art as a GIF, or as numbered
+PNGs if the output isn't a `.gif`.  Neither takes `--shuffle`, `--loop`,
+`--seed` or `--colors`, which only apply when opening a window.
+
+Options:
+      --ordering <ORDERING>  default, side-to-side or spiral
+      --reversed             Play the ordering backwards
+      --by-color             Play one color at a time
+      --by-island            Play one island of touching pixels at a time
+      --game <GAME>          Skip the menu and play appear-test or cart
+      --generate             Skip the menu and watch the ordering generate
+      --shuffle              Play the art in a random order
+      --loop                 Start the art again after the last one
+      --seed <N>             Seed the random number generator
+      --colors <N>           Reduce the art to at most N colors as it loads
+      --json                 Print the `check` report as JSON
+
+Timelapse options:
+      --output <PATH>        Where to save [default: timelapse.gif]
+      --pixels-per-frame <N> Pixels drawn each frame [default: 4]
+      --delay <MS>           How long each frame shows [default: 40]
+      --hold <MS>            How long the finished art shows [default: 2000]
+      --scale <N>            Image pixels per art pixel [default: 8]
+      --grid                 Draw a grid between the art pixels
+      --background <COLOR>   Fill in transparent pixels, as `rrggbb`
+  -h, --help                 Print this message";
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Check the art without opening a window
+    Check { json: bool },
+    /// Save a timelapse without opening a window
+    Timelapse {
+        timelapse: Timelapse,
+        export: ExportSettings,
+        clear_color: Color,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Cli {
+    /// Run instead of opening a window
+    pub command: Option<Command>,
+    pub art: Vec<String>,
+    /// Picked in the main menu when it first shows
+    pub selection: Selection,
+    /// The state to start in instead of the main menu
+    pub start: Option<GameState>,
+    pub shuffle: bool,
+    pub looping: bool,
+    pub seed: Option<u64>,
+    /// Reduces art to this many colors when it's loaded
+    pub colors: Option<usize>,
+}
+
+impl Cli {
+    /// Parses the command line, exiting with a usage message if it's invalid
+    pub fn parse() -> Self {
+        match Self::try_parse(env::args().skip(1)) {
+            Ok(cli) => cli,
+            Err(err) => {
+                eprintln!("error: {err}\n\n{USAGE}");
+                process::exit(2);
+            }
+        }
+    }
+
+    fn try_parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut game = None;
+        let mut generate = false;
+        let mut json = false;
+        let mut timelapse = Timelapse::default();
+        let mut export = ExportSettings::default();
+        let mut background = None;
+        let mut timelapse_option = None;
+        let mut window_option = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("`{arg}` needs a value"));
+            if arg.starts_with("--") && TIMELAPSE_OPTIONS.contains(&arg.as_str()) {
+                timelapse_option = Some(arg.clone());
+            }
+            if WINDOW_OPTIONS.contains(&arg.as_str()) {
+                window_option = Some(arg.clone());
+            }
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    process::exit(0);
+                }
+                "--ordering" => {
+                    let name = value()?;
+                    cli.selection.ordering = OrderingType::ALL
+                        .into_iter()
+                        .find(|typ| typ.name() == name)
+                        .ok_or_else(|| format!("Unknown ordering `{name}`"))?;
+                }
+                "--reversed" => cli.selection.options.reversed = true,
+                "--by-color" => cli.selection.options.by_color = true,
+                "--by-island" => cli.selection.options.by_island = true,
+                "--game" => {
+                    game = Some(match value()?.as_str() {
+                        "appear-test" => GameType::AppearTest,
+                        "cart" => GameType::Cart,
+                        other => return Err(format!("Unknown game `{other}`")),
+                    })
+                }
+                "--generate" => generate = true,
+                "--shuffle" => cli.shuffle = true,
+                "--loop" => cli.looping = true,
+                "--json" => json = true,
+                "--seed" => cli.seed = Some(number(&arg, value()?)?),
+                "--colors" => {
+                    let count = number(&arg, value()?)?;
+                    if !(2..=256).contains(&count) {
+                        return Err("`--colors` needs between 2 and 256 colors".to_owned());
+                    }
+                    cli.colors = Some(count);
+                }
+                "--output" => timelapse.path = value()?,
+                "--pixels-per-frame" => timelapse.pixels_per_frame = number(&arg, value()?)?,
+                "--delay" => timelapse.delay = number(&arg, value()?)?,
+                "--hold" => timelapse.hold = number(&arg, value()?)?,
+                "--scale" => export.scale = number(&arg, value()?)?,
+                "--grid" => export.grid = true,
+                "--background" => {
+                    let hex = value()?;
+                    background = Some(
+                        PixelColor::from_hex(&hex)
+                            .ok_or_else(|| format!("`{hex}` isn't a color"))?,
+                    );
+                }
+                "--" => {
+                    cli.art.extend(args);
+                    break;
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("Unknown option `{flag}`"));
+                }
+                "check" if cli.command.is_none() && cli.art.is_empty() => {
+                    cli.command = Some(Command::Check { json: false });
+                }
+                "timelapse" if cli.command.is_none() && cli.art.is_empty() => {
+                    cli.command = Some(Command::Timelapse {
+                        timelapse: Timelapse::default(),
+                        export: ExportSettings::default(),
+                        clear_color: Color::NONE,
+                    });
+                }
+                "check" | "timelapse" if cli.art.is_empty() => {
+                    return Err("`check` and `timelapse` can't be combined".to_owned());
+                }
+                _ => cli.art.push(arg),
+            }
+        }
+
+        if let (Some(command), Some(option)) = (&cli.command, window_option) {
+            let name = match command {
+                Command::Check { .. } => "check",
+                Command::Timelapse { .. } => "timelapse",
+            };
+            return Err(format!("`{option}` can't be combined with `{name}`"));
+        }
+
+        match &mut cli.command {
+            Some(Command::Check { json: check_json }) => {
+                if game.is_some() || generate {
+                    return Err(
+                        "`check` can't be combined with `--game` or `--generate`".to_owned()
+                    );
+                }
+                if let Some(option) = timelapse_option {
+                    return Err(format!("`{option}` only applies to `timelapse`"));
+                }
+                if cli.art.is_empty() {
+                    return Err("`check` needs some art".to_owned());
+                }
+                *check_json = json;
+                return Ok(cli);
+            }
+            Some(Command::Timelapse {
+                timelapse: command_timelapse,
+                export: command_export,
+                clear_color,
+            }) => {
+                if game.is_some() || generate || json {
+                    return Err(
+                        "`timelapse` can't be combined with `--game`, `--generate` or `--json`"
+                            .to_owned(),
+                    );
+                }
+                if cli.art.len() != 1 {
+                    return Err("`timelapse` needs one art".to_owned());
+                }
+                export.background = background.is_some();
+                *command_timelapse = timelapse;
+                *command_export = export;
+                *clear_color = background.map_or(Color::NONE, Color::from);
+                return Ok(cli);
+            }
+            None => {}
+        }
+        if json {
+            return Err("`--json` only applies to `check`".to_owned());
+        }
+        if let Some(option) = timelapse_option {
+            return Err(format!("`{option}` only applies to `timelapse`"));
+        }
+
+        cli.start = match (game, generate) {
+            (Some(_), true) => return Err("`--game` and `--generate` can't be combined".to_owned()),
+            (Some(game), false) => {
+                cli.selection.game = game;
+                Some(GameState::Play(game))
+            }
+            (None, true) => {
+                if cli.selection.options != Default::default() {
+                    return Err(
+                        "`--reversed`, `--by-color` and `--by-island` only apply to `--game`, not \
+                         `--generate`"
+                            .to_owned(),
+                    );
+                }
+                Some(GameState::Generate(cli.selection.ordering))
+            }
+            (None, false) => None,
+        };
+        if cli.start.is_some() && cli.art.is_empty() {
+            return Err("Skipping the menu needs some art".to_owned());
+        }
+        Ok(cli)
+    }
+}
+
+/// The options that only apply to `timelapse`
+const TIMELAPSE_OPTIONS: [&str; 7] = [
+    "--output",
+    "--pixels-per-frame",
+    "--delay",
+    "--hold",
+    "--scale",
+    "--grid",
+    "--background",
+];
+
+/// The options that only apply when opening a window
+const WINDOW_OPTIONS: [&str; 4] = ["--shuffle", "--loop", "--seed", "--colors"];
+
+fn number<T: FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{arg}` needs a number, not `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::try_parse(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn parses_playlist() {
+        let cli = parse(&["--shuffle", "--seed", "7", "a.png", "b.png"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.art, ["a.png", "b.png"]);
+        assert!(cli.shuffle);
+        assert_eq!(cli.seed, Some(7));
+    }
+
+    #[test]
+    fn rejects_unknown_option() {
+        assert_eq!(
+            parse(&["--bogus", "a.png"]).unwrap_err(),
+            "Unknown option `--bogus`"
+        );
+        assert!(parse(&["--ordering", "zigzag", "a.png"]).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_value() {
+        assert_eq!(
+            parse(&["a.png", "--seed"]).unwrap_err(),
+            "`--seed` needs a value"
+        );
+        assert_eq!(
+            parse(&["timelapse", "a.png", "--scale"]).unwrap_err(),
+            "`--scale` needs a value"
+        );
+        assert!(parse(&["--colors", "many", "a.png"]).is_err());
+    }
+
+    #[test]
+    fn rejects_conflicting_commands() {
+        assert!(parse(&["check", "timelapse", "a.png"]).is_err());
+        assert!(parse(&["timelapse", "check", "a.png"]).is_err());
+        assert!(parse(&["check", "--game", "cart", "a.png"]).is_err());
+        assert!(parse(&["timelapse", "--json", "a.png"]).is_err());
+        assert!(parse(&["check", "--grid", "a.png"]).is_err());
+        assert!(parse(&["--game", "cart", "--generate", "a.png"]).is_err());
+
+        // Art that happens to be named like a command still loads
+        assert_eq!(
+            parse(&["check", "a.png", "check"]).unwrap().art,
+            ["a.png", "check"]
+        );
+    }
+
+    #[test]
+    fn rejects_window_options_with_commands() {
+        for option in [
+            &["--shuffle"][..],
+            &["--loop"],
+            &["--seed", "1"],
+            &["--colors", "4"],
+        ] {
+            for command in ["check", "timelapse"] {
+                let args: Vec<_> = [command]
+                    .iter()
+                    .chain(option)
+                    .chain(&["a.png"])
+                    .copied()
+                    .collect();
+                assert_eq!(
+                    parse(&args).unwrap_err(),
+                    format!("`{}` can't be combined with `{command}`", option[0])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parses_timelapse() {
+        let cli = parse(&[
+            "timelapse",
+            "--scale",
+            "2",
+            "--grid",
+            "--background",
+            "102030",
+            "a.png",
+        ])
+        .unwrap();
+        let Some(Command::Timelapse {
+            export,
+            clear_color,
+            ..
+        }) = cli.command
+        else {
+            panic!("not a timelapse");
+        };
+        assert_eq!(export.scale, 2);
+        assert!(export.grid);
+        assert!(export.background);
+        assert_ne!(clear_color, Color::NONE);
+    }
+}
@@ -0,0 +1,450 @@
+//! A small pixel editor for making art, or touching it up, before playing
+
+use std::{collections::VecDeque, mem, ops::ControlFlow, path::Path};
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_egui::{egui, EguiContext};
+use image::RgbaImage;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{Art, ArtFrames, ArtName, ArtPath, PixelColor},
+    graph,
+    grid::Grid,
+    ordering::Orderings,
+    palette::Palette,
+    playlist::Playlist,
+    status::Status,
+    GameState,
+};
+
+/// How many steps can be undone
+const MAX_UNDO: usize = 100;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(editor_system.run_in_state(GameState::Editor));
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tool {
+    #[default]
+    Pencil,
+    Eraser,
+    Fill,
+    Eyedropper,
+}
+
+#[derive(Debug, Resource)]
+pub struct Editor {
+    name: String,
+    canvas: Grid<Option<PixelColor>>,
+    undo: Vec<Grid<Option<PixelColor>>>,
+    redo: Vec<Grid<Option<PixelColor>>>,
+    tool: Tool,
+    color: PixelColor,
+    /// The cell painted last in the current stroke, if there is one
+    last_cell: Option<UVec2>,
+    /// The canvas from before the current stroke, to undo back to if the
+    /// stroke changes anything
+    stroke_start: Option<Grid<Option<PixelColor>>>,
+    new_size: UVec2,
+    save_path: String,
+}
+
+impl Editor {
+    pub fn new(size: UVec2) -> Self {
+        Self::from_grid("Untitled".to_owned(), Grid::new(size))
+    }
+
+    pub fn from_art(name: String, art: &Art) -> Self {
+        Self::from_grid(name, art.grid().clone())
+    }
+
+    fn from_grid(name: String, canvas: Grid<Option<PixelColor>>) -> Self {
+        let stem = Path::new(&name)
+            .file_stem()
+            .map_or_else(|| name.clone(), |stem| stem.to_string_lossy().into_owned());
+        Self {
+            save_path: format!("{stem}.png"),
+            name,
+            new_size: canvas.size(),
+            canvas,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            tool: Tool::default(),
+            color: [0, 0, 0, 255].into(),
+            last_cell: None,
+            stroke_start: None,
+        }
+    }
+
+    /// Saves the canvas so the next change can be undone
+    fn checkpoint(&mut self) {
+        self.push_undo(self.canvas.clone());
+    }
+
+    fn push_undo(&mut self, canvas: Grid<Option<PixelColor>>) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(canvas);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(canvas) = self.undo.pop() {
+            self.redo.push(mem::replace(&mut self.canvas, canvas));
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(canvas) = self.redo.pop() {
+            self.undo.push(mem::replace(&mut self.canvas, canvas));
+        }
+    }
+
+    /// Paints every cell on the line from `from` to `to`
+    fn paint_line(&mut self, from: UVec2, to: UVec2, color: Option<PixelColor>) {
+        let (from, to) = (from.as_vec2(), to.as_vec2());
+        let steps = (to - from).abs().max_element() as u32;
+        for step in 0..=steps {
+            let t = if steps == 0 {
+                0.0
+            } else {
+                step as f32 / steps as f32
+            };
+            if let Some(cell) = self.canvas.get_mut(from.lerp(to, t).round().as_uvec2()) {
+                *cell = color;
+            }
+        }
+    }
+
+    /// Paints the area of matching cells connected to `start`
+    fn fill(&mut self, start: UVec2, color: Option<PixelColor>) {
+        let target = self.canvas[start];
+        if target == color {
+            return;
+        }
+
+        let canvas = &self.canvas;
+        let mut filled = Vec::new();
+        graph::bfs(
+            &mut VecDeque::from([(start, 0)]),
+            &mut HashSet::new(),
+            |pos, _| {
+                filled.push(pos);
+                ControlFlow::<(), _>::Continue(pos)
+            },
+            |pos: UVec2| {
+                canvas
+                    .neighbors_4(pos)
+                    .filter(move |&pos| canvas[pos] == target)
+            },
+        );
+        for pos in filled {
+            self.canvas[pos] = color;
+        }
+    }
+
+    /// The color the pencil and fill paint with.  A fully transparent color
+    /// erases instead, so the canvas never holds invisible pixels
+    fn paint_color(&self) -> Option<PixelColor> {
+        Some(self.color).filter(|color| color.rgba()[3] != 0)
+    }
+
+    /// Applies the current tool to `cell`
+    fn use_tool(&mut self, cell: UVec2) {
+        let stroke_started = self.last_cell.is_none();
+        if stroke_started && self.tool != Tool::Eyedropper {
+            self.stroke_start = Some(self.canvas.clone());
+        }
+
+        match self.tool {
+            Tool::Pencil => {
+                self.paint_line(self.last_cell.unwrap_or(cell), cell, self.paint_color())
+            }
+            Tool::Eraser => self.paint_line(self.last_cell.unwrap_or(cell), cell, None),
+            Tool::Fill if stroke_started => self.fill(cell, self.paint_color()),
+            Tool::Fill => {}
+            Tool::Eyedropper => {
+                if let Some(color) = self.canvas[cell] {
+                    self.color = color;
+                }
+            }
+        }
+        self.last_cell = Some(cell);
+    }
+
+    /// Finishes the current stroke, keeping an undo step only if it changed
+    /// the canvas
+    fn end_stroke(&mut self) {
+        self.last_cell = None;
+        if let Some(canvas) = self.stroke_start.take() {
+            if canvas != self.canvas {
+                self.push_undo(canvas);
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let image = RgbaImage::from_fn(self.canvas.width(), self.canvas.height(), |x, y| {
+            image::Rgba(self.canvas[UVec2::new(x, y)].map_or([0; 4], PixelColor::rgba))
+        });
+        image
+            .save(path)
+            .map_err(|e| format!("Unable to save image: {e}"))
+    }
+}
+
+fn editor_system(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut editor: ResMut<Editor>,
+    mut orderings: ResMut<Orderings>,
+    art: Option<Res<Art>>,
+    palette: Option<Res<Palette>>,
+) {
+    let ctx = egui_context.ctx_mut();
+    if !ctx.wants_keyboard_input() {
+        let input = ctx.input();
+        if input.modifiers.command && input.key_pressed(egui::Key::Z) {
+            if input.modifiers.shift {
+                editor.redo();
+            } else {
+                editor.undo();
+            }
+        } else if input.modifiers.command && input.key_pressed(egui::Key::Y) {
+            editor.redo();
+        }
+    }
+
+    egui::SidePanel::left("editor_tools")
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.heading("Editor");
+
+            ui.horizontal_wrapped(|ui| {
+                for (tool, label) in [
+                    (Tool::Pencil, "Pencil"),
+                    (Tool::Eraser, "Eraser"),
+                    (Tool::Fill, "Fill"),
+                    (Tool::Eyedropper, "Eyedropper"),
+                ] {
+                    ui.selectable_value(&mut editor.tool, tool, label);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let mut rgba = editor.color.rgba();
+                if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+                    editor.color = rgba.into();
+                }
+                ui.label("Color");
+            });
+
+            let mut swatches = palette
+                .as_ref()
+                .map_or_else(Vec::new, |palette| palette.colors.clone());
+            let mut seen: HashSet<_> = swatches.iter().copied().collect();
+            swatches.extend(
+                editor
+                    .canvas
+                    .rows()
+                    .flatten()
+                    .flatten()
+                    .copied()
+                    .filter(|&color| seen.insert(color)),
+            );
+            ui.horizontal_wrapped(|ui| {
+                for color in swatches {
+                    if color_button(ui, color, color == editor.color).clicked() {
+                        editor.color = color;
+                        if editor.tool == Tool::Eraser || editor.tool == Tool::Eyedropper {
+                            editor.tool = Tool::Pencil;
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!editor.undo.is_empty(), egui::Button::new("Undo"))
+                    .clicked()
+                {
+                    editor.undo();
+                }
+                if ui
+                    .add_enabled(!editor.redo.is_empty(), egui::Button::new("Redo"))
+                    .clicked()
+                {
+                    editor.redo();
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut editor.new_size.x).clamp_range(2..=512));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut editor.new_size.y).clamp_range(2..=512));
+                if ui.button("New Canvas").clicked() {
+                    editor.checkpoint();
+                    editor.canvas = Grid::new(editor.new_size);
+                }
+            });
+
+            ui.separator();
+            ui.text_edit_singleline(&mut editor.save_path);
+            if ui.button("Save PNG").clicked() {
+                let message = match editor.save(Path::new(&editor.save_path)) {
+                    Ok(()) => format!("Saved {}", editor.save_path),
+                    Err(err) => err,
+                };
+                commands.insert_resource(Status::new(message));
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Play This").clicked() {
+                    match Art::from_grid(editor.canvas.clone()) {
+                        Ok(art) => {
+                            commands.insert_resource(ArtFrames::from(art.clone()));
+                            commands.insert_resource(art);
+                            commands.insert_resource(ArtName(editor.name.clone()));
+                            commands.remove_resource::<ArtPath>();
+                            commands.remove_resource::<Playlist>();
+                            orderings.clear();
+                            commands.insert_resource(NextState(GameState::MainMenu));
+                        }
+                        Err(err) => commands.insert_resource(Status::new(err)),
+                    }
+                }
+                if ui.button("Back").clicked() {
+                    commands.insert_resource(NextState(if art.is_some() {
+                        GameState::MainMenu
+                    } else {
+                        GameState::AwaitingImage
+                    }));
+                }
+            });
+        });
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let size = editor.canvas.size().as_vec2();
+        let available = ui.available_size();
+        let scale = (available.x / size.x)
+            .min(available.y / size.y)
+            .floor()
+            .max(1.0);
+        let rect = egui::Rect::from_center_size(
+            ui.max_rect().center(),
+            egui::vec2(size.x * scale, size.y * scale),
+        );
+        let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+
+        let painter = ui.painter_at(rect);
+        for (y, row) in editor.canvas.rows().enumerate() {
+            for (x, &color) in row.iter().enumerate() {
+                let min = rect.min + egui::vec2(x as f32, y as f32) * scale;
+                let cell = egui::Rect::from_min_size(min, egui::vec2(scale, scale));
+                let fill = match color {
+                    Some(color) => {
+                        let [r, g, b, a] = color.rgba();
+                        egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+                    }
+                    // Checkerboard, so transparent cells stand out
+                    None if (x + y) % 2 == 0 => egui::Color32::from_gray(170),
+                    None => egui::Color32::from_gray(140),
+                };
+                painter.rect_filled(cell, 0.0, fill);
+            }
+        }
+
+        match response.interact_pointer_pos() {
+            Some(pos) if response.is_pointer_button_down_on() => {
+                let cell = ((pos - rect.min) / scale).floor();
+                let cell = Vec2::new(cell.x, cell.y)
+                    .clamp(Vec2::ZERO, size - 1.0)
+                    .as_uvec2();
+                editor.use_tool(cell);
+            }
+            _ => editor.end_stroke(),
+        }
+    });
+}
+
+/// A clickable swatch, outlined when `selected`
+fn color_button(ui: &mut egui::Ui, color: PixelColor, selected: bool) -> egui::Response {
+    let [r, g, b, a] = color.rgba();
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::click());
+    ui.painter()
+        .rect_filled(rect, 2.0, egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+    if selected {
+        ui.painter()
+            .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_path_replaces_extension() {
+        let editor = Editor::from_grid("foo.png".to_owned(), Grid::new(UVec2::splat(2)));
+        assert_eq!(editor.save_path, "foo.png");
+        let editor = Editor::from_grid("Untitled".to_owned(), Grid::new(UVec2::splat(2)));
+        assert_eq!(editor.save_path, "Untitled.png");
+    }
+
+    #[test]
+    fn stroke_is_undoable() {
+        let mut editor = Editor::new(UVec2::splat(4));
+        editor.use_tool(UVec2::ZERO);
+        editor.use_tool(UVec2::new(3, 0));
+        editor.end_stroke();
+        assert_eq!(editor.undo.len(), 1);
+        assert!(editor.canvas[UVec2::new(1, 0)].is_some());
+
+        editor.undo();
+        assert_eq!(editor.canvas, Grid::new(UVec2::splat(4)));
+    }
+
+    #[test]
+    fn unchanged_canvas_is_not_checkpointed() {
+        let mut editor = Editor::new(UVec2::splat(4));
+        editor.tool = Tool::Eraser;
+        editor.use_tool(UVec2::ZERO);
+        editor.end_stroke();
+        assert!(editor.undo.is_empty());
+
+        editor.tool = Tool::Fill;
+        editor.use_tool(UVec2::ZERO);
+        editor.end_stroke();
+        editor.use_tool(UVec2::ZERO);
+        editor.end_stroke();
+        assert_eq!(editor.undo.len(), 1);
+    }
+
+    #[test]
+    fn transparent_color_erases() {
+        let mut editor = Editor::new(UVec2::splat(4));
+        editor.use_tool(UVec2::ZERO);
+        editor.end_stroke();
+
+        editor.color = [255, 0, 0, 0].into();
+        editor.use_tool(UVec2::ZERO);
+        editor.end_stroke();
+        assert_eq!(editor.canvas[UVec2::ZERO], None);
+
+        editor.tool = Tool::Fill;
+        editor.use_tool(UVec2::ONE);
+        editor.end_stroke();
+        assert_eq!(editor.canvas, Grid::new(UVec2::splat(4)));
+    }
+}
@@ -0,0 +1,256 @@
+//! Draws the art as far as the current game has got into an image, without
+//! the GPU, so the picture can be saved as a PNG
+
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashSet};
+use image::RgbaImage;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{Art, PixelColor},
+    input::EXPORT_KEYS,
+    ordering::CurrentOrdering,
+    status::Status,
+    GameState,
+};
+
+/// How much of the cell the grid lines darken, out of 255
+const GRID_DARKEN: u8 = 64;
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExportSettings>().add_system(
+            export_key_system
+                .run_if(GameState::current_is_play)
+                .run_if_resource_exists::<CurrentOrdering>(),
+        );
+    }
+}
+
+/// How exported pictures are drawn, set in the art options
+#[derive(Debug, Clone, Resource)]
+pub struct ExportSettings {
+    /// How many image pixels each art pixel covers along each axis
+    pub scale: u32,
+    /// Draws a line along the bottom and right of each art pixel
+    pub grid: bool,
+    /// Fills in the transparent pixels with the [`ClearColor`]
+    pub background: bool,
+    pub path: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            scale: 8,
+            grid: false,
+            background: false,
+            path: "pixel_art.png".to_owned(),
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Draws `art`, leaving out the pixels that `ordering` hasn't reached
+    /// yet.  With no ordering the whole art is drawn
+    pub fn render(
+        &self,
+        art: &Art,
+        ordering: Option<&CurrentOrdering>,
+        clear_color: Color,
+    ) -> RgbaImage {
+        let hidden: HashSet<UVec2> = ordering
+            .map(|ordering| ordering.remaining().iter().map(|pixel| pixel.pos).collect())
+            .unwrap_or_default();
+        let scale = self.scale.max(1);
+        let mut image = RgbaImage::new(art.width() * scale, art.height() * scale);
+        for y in 0..art.height() {
+            for x in 0..art.width() {
+                let pos = UVec2::new(x, y);
+                let color = art[pos].filter(|_| !hidden.contains(&pos));
+                self.draw_pixel(&mut image, pos, color, clear_color);
+            }
+        }
+        image
+    }
+
+    /// Draws the art pixel at `pos` into an image made by [`Self::render`]
+    pub fn draw_pixel(
+        &self,
+        image: &mut RgbaImage,
+        pos: UVec2,
+        color: Option<PixelColor>,
+        clear_color: Color,
+    ) {
+        let scale = self.scale.max(1);
+        let mut color = color.map_or([0; 4], PixelColor::rgba);
+        if self.background {
+            color = blend(color, clear_color.as_rgba_u32().to_le_bytes());
+        }
+        for y in 0..scale {
+            for x in 0..scale {
+                let mut color = color;
+                if self.grid && scale > 1 && (x == scale - 1 || y == scale - 1) {
+                    color = blend([0, 0, 0, GRID_DARKEN], color);
+                }
+                image.put_pixel(pos.x * scale + x, pos.y * scale + y, image::Rgba(color));
+            }
+        }
+    }
+
+    /// Renders and saves to [`Self::path`], returning a message for the user
+    pub fn save(
+        &self,
+        art: &Art,
+        ordering: Option<&CurrentOrdering>,
+        clear_color: Color,
+    ) -> Result<String, String> {
+        self.render(art, ordering, clear_color)
+            .save(Path::new(&self.path))
+            .map_err(|e| format!("Unable to save image: {e}"))?;
+        Ok(format!("Saved {}", self.path))
+    }
+}
+
+/// Draws `over` on top of `under`, with straight alpha
+fn blend(over: [u8; 4], under: [u8; 4]) -> [u8; 4] {
+    let over_a = over[3] as u32;
+    let under_a = under[3] as u32 * (255 - over_a) / 255;
+    let a = over_a + under_a;
+    if a == 0 {
+        return [0; 4];
+    }
+    let channel = |i: usize| ((over[i] as u32 * over_a + under[i] as u32 * under_a) / a) as u8;
+    [channel(0), channel(1), channel(2), a as u8]
+}
+
+/// Saves the picture so far while playing
+fn export_key_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<ExportSettings>,
+    art: Res<Art>,
+    ordering: Res<CurrentOrdering>,
+    clear_color: Res<ClearColor>,
+) {
+    if keys.any_just_pressed(EXPORT_KEYS) {
+        let message = settings
+            .save(&art, Some(&ordering), clear_color.0)
+            .unwrap_or_else(|err| err);
+        info!("{message}");
+        commands.insert_resource(Status::new(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::Grid,
+        ordering::{OrderingType, Orderings},
+    };
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const HALF_BLUE: [u8; 4] = [0, 0, 255, 128];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+    /// Red and half-transparent blue along the top, then a hole and green
+    fn art() -> Art {
+        Art::from_grid(Grid::from_vec(
+            vec![
+                Some(RED.into()),
+                Some(HALF_BLUE.into()),
+                None,
+                Some(GREEN.into()),
+            ],
+            2,
+        ))
+        .unwrap()
+    }
+
+    fn settings(scale: u32, grid: bool, background: bool) -> ExportSettings {
+        ExportSettings {
+            scale,
+            grid,
+            background,
+            ..default()
+        }
+    }
+
+    fn pixel(image: &RgbaImage, x: u32, y: u32) -> [u8; 4] {
+        image.get_pixel(x, y).0
+    }
+
+    #[test]
+    fn scales_each_pixel() {
+        let image = settings(3, false, false).render(&art(), None, Color::NONE);
+        assert_eq!(image.dimensions(), (6, 6));
+        for (x, y) in [(0, 0), (2, 2), (0, 2)] {
+            assert_eq!(pixel(&image, x, y), RED);
+        }
+        assert_eq!(pixel(&image, 3, 0), HALF_BLUE);
+        assert_eq!(pixel(&image, 5, 5), GREEN);
+    }
+
+    #[test]
+    fn transparent_without_background() {
+        let image = settings(1, false, false).render(&art(), None, Color::WHITE);
+        assert_eq!(pixel(&image, 0, 1), [0; 4]);
+        assert_eq!(pixel(&image, 1, 0), HALF_BLUE);
+    }
+
+    #[test]
+    fn hidden_pixels_show_background() {
+        let art = art();
+        let mut ordering = CurrentOrdering::init(
+            &mut Orderings::default(),
+            OrderingType::SideToSide,
+            default(),
+            &art,
+        );
+        ordering.next();
+
+        let background = Color::rgba_u8(10, 20, 30, 255);
+        let image = settings(1, false, true).render(&art, Some(&ordering), background);
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 1, 0), [10, 20, 30, 255]);
+        assert_eq!(pixel(&image, 0, 1), [10, 20, 30, 255]);
+        assert_eq!(pixel(&image, 1, 1), [10, 20, 30, 255]);
+
+        let image = settings(1, false, false).render(&art, Some(&ordering), background);
+        assert_eq!(pixel(&image, 1, 1), [0; 4]);
+    }
+
+    #[test]
+    fn grid_darkens_bottom_and_right() {
+        let image = settings(4, true, false).render(&art(), None, Color::NONE);
+        assert_eq!(pixel(&image, 0, 0), RED);
+        assert_eq!(pixel(&image, 2, 2), RED);
+        for (x, y) in [(3, 0), (0, 3), (3, 3)] {
+            assert_eq!(pixel(&image, x, y), [191, 0, 0, 255]);
+        }
+        assert_eq!(pixel(&image, 0, 7), [0, 0, 0, GRID_DARKEN]);
+
+        // A single image pixel per art pixel leaves no room for the grid
+        let image = settings(1, true, false).render(&art(), None, Color::NONE);
+        assert_eq!(pixel(&image, 0, 0), RED);
+    }
+
+    #[test]
+    fn blends_partial_alpha() {
+        assert_eq!(
+            blend([255, 0, 0, 128], [0, 0, 255, 255]),
+            [128, 0, 127, 255]
+        );
+        assert_eq!(blend([255, 0, 0, 128], [0; 4]), [255, 0, 0, 128]);
+        assert_eq!(
+            blend([255, 255, 255, 128], [0, 0, 0, 128]),
+            [170, 170, 170, 191]
+        );
+        assert_eq!(blend(RED, GREEN), RED);
+        assert_eq!(blend([0; 4], [0; 4]), [0; 4]);
+    }
+}
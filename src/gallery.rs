@@ -0,0 +1,251 @@
+//! Recently opened art and the art folder, kept in the user's data
+//! directory between runs
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{Art, ArtFrames, ArtName},
+    menu,
+    ordering::Orderings,
+    palette::ColorReduction,
+    status::Status,
+    GameState,
+};
+
+const MAX_RECENT: usize = 12;
+
+const THUMBNAIL_SIZE: f32 = 96.0;
+
+/// How many thumbnails are made each frame, so opening a big folder
+/// doesn't freeze the window
+const THUMBNAILS_PER_FRAME: usize = 4;
+
+pub struct GalleryPlugin;
+
+impl Plugin for GalleryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(gallery_system.run_in_state(GameState::Gallery));
+    }
+}
+
+#[derive(Resource)]
+pub struct Gallery {
+    recent: Vec<PathBuf>,
+    folder: Option<PathBuf>,
+    /// The art in [`Self::folder`]
+    folder_art: Vec<PathBuf>,
+    folder_text: String,
+    /// `None` if the art couldn't be loaded
+    thumbnails: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+
+impl Gallery {
+    /// Loads the gallery saved by the last run, or an empty one
+    pub fn load() -> Self {
+        let mut gallery = Self {
+            recent: Vec::new(),
+            folder: None,
+            folder_art: Vec::new(),
+            folder_text: String::new(),
+            thumbnails: HashMap::new(),
+        };
+        let text = save_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("folder", path)) => gallery.set_folder(PathBuf::from(path)),
+                Some(("recent", path)) => gallery.recent.push(PathBuf::from(path)),
+                _ => {}
+            }
+        }
+        gallery
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = save_path().ok_or("No data directory")?;
+        let mut text = String::new();
+        if let Some(folder) = &self.folder {
+            text += &format!("folder {}\n", folder.display());
+        }
+        for recent in &self.recent {
+            text += &format!("recent {}\n", recent.display());
+        }
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|()| fs::write(&path, text))
+            .map_err(|e| format!("Unable to save gallery: {e}"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty() && self.folder_art.is_empty()
+    }
+
+    /// Moves `path` to the front of the recent files
+    pub fn add_recent(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.recent.retain(|recent| *recent != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+        if let Err(err) = self.save() {
+            warn!("{err}");
+        }
+    }
+
+    fn set_folder(&mut self, folder: PathBuf) {
+        self.folder_text = folder.display().to_string();
+        self.folder_art = fs::read_dir(&folder)
+            .map(|dir| {
+                dir.filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.is_file())
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.folder_art.sort();
+        self.folder = Some(folder);
+    }
+
+    /// Makes a few of the missing thumbnails for `paths`
+    fn make_thumbnails(&mut self, ctx: &egui::Context, paths: &[PathBuf]) {
+        let missing: Vec<_> = paths
+            .iter()
+            .filter(|path| !self.thumbnails.contains_key(*path))
+            .take(THUMBNAILS_PER_FRAME)
+            .cloned()
+            .collect();
+        for path in missing {
+            let thumbnail = ArtFrames::load_from_path(&path)
+                .ok()
+                .map(|frames| thumbnail(ctx, &path, &frames.current()));
+            self.thumbnails.insert(path, thumbnail);
+        }
+    }
+
+    /// Shows a clickable thumbnail for each of `paths`, returning the one
+    /// that was clicked
+    fn show_thumbnails(&self, ui: &mut egui::Ui, paths: &[PathBuf]) -> Option<PathBuf> {
+        let mut clicked = None;
+        ui.horizontal_wrapped(|ui| {
+            for path in paths {
+                ui.vertical(|ui| {
+                    ui.set_width(THUMBNAIL_SIZE);
+                    let response = match self.thumbnails.get(path) {
+                        Some(Some(texture)) => {
+                            let size = texture.size_vec2();
+                            let scale = THUMBNAIL_SIZE / size.x.max(size.y);
+                            ui.add(egui::ImageButton::new(texture, size * scale))
+                        }
+                        Some(None) => ui.add_sized(
+                            [THUMBNAIL_SIZE, THUMBNAIL_SIZE],
+                            egui::Button::new("Can't load"),
+                        ),
+                        None => ui.add_sized(
+                            [THUMBNAIL_SIZE, THUMBNAIL_SIZE],
+                            egui::Button::new("Loading…"),
+                        ),
+                    };
+                    if response.clicked() {
+                        clicked = Some(path.clone());
+                    }
+                    ui.label(ArtName::from_path(path).0);
+                });
+            }
+        });
+        clicked
+    }
+}
+
+fn save_path() -> Option<PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("pixel_art_games")
+            .join("gallery.txt"),
+    )
+}
+
+fn thumbnail(ctx: &egui::Context, path: &Path, art: &Art) -> egui::TextureHandle {
+    let image = art.to_image();
+    ctx.load_texture(
+        path.display().to_string(),
+        egui::ColorImage::from_rgba_unmultiplied(
+            [image.width() as usize, image.height() as usize],
+            image.as_raw(),
+        ),
+        egui::TextureOptions::NEAREST,
+    )
+}
+
+fn gallery_system(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut gallery: ResMut<Gallery>,
+    mut orderings: ResMut<Orderings>,
+    color_reduction: Res<ColorReduction>,
+    art: Option<Res<Art>>,
+) {
+    let ctx = egui_context.ctx_mut();
+    let recent = gallery.recent.clone();
+    let folder_art = gallery.folder_art.clone();
+    gallery.make_thumbnails(ctx, &recent);
+    gallery.make_thumbnails(ctx, &folder_art);
+
+    let mut clicked = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.heading("Gallery");
+            if ui.button("Back").clicked() {
+                commands.insert_resource(NextState(if art.is_some() {
+                    GameState::MainMenu
+                } else {
+                    GameState::AwaitingImage
+                }));
+            }
+        });
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if !recent.is_empty() {
+                egui::CollapsingHeader::new("Recent")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if let Some(path) = gallery.show_thumbnails(ui, &recent) {
+                            clicked = Some(path);
+                        }
+                    });
+            }
+
+            egui::CollapsingHeader::new("Art Folder")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut gallery.folder_text);
+                        if ui.button("Open").clicked() {
+                            let folder = PathBuf::from(&gallery.folder_text);
+                            gallery.set_folder(folder);
+                            if let Err(err) = gallery.save() {
+                                commands.insert_resource(Status::new(err));
+                            }
+                        }
+                    });
+                    if let Some(path) = gallery.show_thumbnails(ui, &folder_art) {
+                        clicked = Some(path);
+                    }
+                });
+        });
+    });
+
+    if let Some(path) = clicked {
+        menu::open_art(
+            &mut commands,
+            &path,
+            &mut orderings,
+            &mut gallery,
+            &color_reduction,
+        );
+    }
+}
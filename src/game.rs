@@ -1,12 +1,19 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 use bevy_egui::egui;
 use bevy_egui::EguiContext;
 use iyes_loopless::prelude::*;
 
-use crate::{input::EXIT_KEYS, GameState};
+use crate::{
+    art::{Art, ArtFrames},
+    input::EXIT_KEYS,
+    mesh_generation::MulticolorMesh,
+    ordering::{CurrentOrdering, Orderings},
+    GameState,
+};
 
 mod appear_test;
 mod cart;
+mod completion;
 mod pixel;
 
 pub struct GamePlugin;
@@ -15,9 +22,17 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(appear_test::AppearTestPlugin)
             .add_plugin(cart::CartPlugin)
+            .add_plugin(completion::CompletionPlugin)
             .add_plugin(pixel::PixelPlugin)
+            .add_event::<FrameAdvanced>()
             .add_startup_system(startup_system)
             .add_system(exit_game_system.run_if_not(GameState::current_is_menu))
+            .add_system(
+                next_frame_system
+                    .run_if(GameState::current_is_play)
+                    .run_if_resource_exists::<CurrentOrdering>(),
+            )
+            .add_system(remove_pixels_system.run_if(GameState::current_is_play))
             .add_system(set_colors_system.run_if_not(GameState::current_is_menu));
     }
 }
@@ -29,6 +44,18 @@ pub enum GameType {
     Cart,
 }
 
+impl GameType {
+    pub const ALL: [Self; 2] = [Self::AppearTest, Self::Cart];
+}
+
+/// Sent when the current ordering is finished and the next frame of
+/// [`ArtFrames`] has been started
+#[derive(Debug, Clone)]
+pub struct FrameAdvanced {
+    /// Pixels that were visible in the last frame but aren't in this one
+    pub removed: Vec<UVec2>,
+}
+
 #[derive(Debug, Resource)]
 pub struct Colors {
     pub primary_color: Color,
@@ -70,6 +97,50 @@ fn exit_game_system(mut commands: Commands, keys: Res<Input<KeyCode>>) {
     }
 }
 
+fn next_frame_system(
+    mut art: ResMut<Art>,
+    mut frames: ResMut<ArtFrames>,
+    mut orderings: ResMut<Orderings>,
+    mut ordering: ResMut<CurrentOrdering>,
+    mut frame_events: EventWriter<FrameAdvanced>,
+) {
+    if frames.animate && ordering.is_finished() {
+        if let Some(next) = frames.next() {
+            frames.current += 1;
+            orderings.clear();
+            *ordering = CurrentOrdering::init_changed(
+                &mut orderings,
+                ordering.typ(),
+                ordering.options(),
+                &art,
+                &next,
+            );
+            frame_events.send(FrameAdvanced {
+                removed: next.removed_from(&art),
+            });
+            *art = next;
+        }
+    }
+}
+
+/// Erases pixels that the new frame made transparent, since orderings only
+/// add pixels
+fn remove_pixels_system(
+    mut frame_events: EventReader<FrameAdvanced>,
+    mut mesh_query: Query<&mut MulticolorMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for event in frame_events.iter() {
+        if event.removed.is_empty() {
+            continue;
+        }
+        let removed: HashSet<_> = event.removed.iter().copied().collect();
+        for mut mesh in &mut mesh_query {
+            mesh.edit(&mut meshes).remove_pixels(&removed);
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn set_colors_system(
     mut egui_context: ResMut<EguiContext>,
@@ -37,7 +37,7 @@ fn enter_system(
 }
 
 fn step_system(
-    query: Query<&MulticolorMesh>,
+    mut query: Query<&mut MulticolorMesh>,
     mut order: ResMut<CurrentOrdering>,
     mut meshes: ResMut<Assets<Mesh>>,
     keys: Res<Input<KeyCode>>,
@@ -47,7 +47,7 @@ fn step_system(
     if keys.any_just_pressed(FORWARD_KEYS) {
         *state = State::Wait(Timer::new(WAIT_REPEAT_DURATION, TimerMode::Once));
         query
-            .single()
+            .single_mut()
             .edit(&mut meshes)
             .add_next_from_ordering(&mut order);
     } else if keys.any_pressed(FORWARD_KEYS) {
@@ -59,7 +59,8 @@ fn step_system(
                 }
             }
             State::Repeat(timer) => {
-                let mut editor = query.single().edit(&mut meshes);
+                let mut mesh = query.single_mut();
+                let mut editor = mesh.edit(&mut meshes);
                 for _ in 0..timer.tick(time.delta()).times_finished_this_tick() {
                     editor.add_next_from_ordering(&mut order);
                 }
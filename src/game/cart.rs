@@ -1,5 +1,3 @@
-use std::{collections::VecDeque, ops::ControlFlow};
-
 use bevy::{
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task},
@@ -9,15 +7,20 @@ use iyes_loopless::prelude::*;
 
 use crate::{
     art::Art,
-    grid::Grid,
-    input::{LEFT_KEYS, RIGHT_KEYS},
-    mesh_generation::{MulticolorMesh, MulticolorMeshMaterial},
-    ordering::CurrentOrdering,
-    side::{Corner, Edge, LeftRight, Side},
+    bit_grid::BitGrid,
+    graph,
+    input::{HINT_KEYS, LEFT_KEYS, RIGHT_KEYS},
+    mesh_generation::{MulticolorMesh, MulticolorMeshEditor, MulticolorMeshMaterial},
+    ordering::{lines::Line, CurrentOrdering},
+    side::{Corner, Edge, Side},
     world_pos, GameState,
 };
 
-use super::{pixel::next_pixel::NextPixel, Colors, GameType};
+use self::contours::{edge_neighbors, Contours};
+
+use super::{pixel::next_pixel::NextPixel, ColorType, Colors, FrameAdvanced, GameType};
+
+mod contours;
 
 const STATE: GameState = GameState::Play(GameType::Cart);
 
@@ -59,6 +62,14 @@ impl Plugin for CartPlugin {
                     .run_in_state(STATE)
                     .run_if_resource_exists::<FindZipPathTask>(),
             )
+            .add_system(
+                retry_zip_search_system
+                    .run_in_state(STATE)
+                    .after(CartSystem::MoveCart)
+                    .run_if_resource_exists::<RetryZipSearch>(),
+            )
+            .add_system(next_frame_system.run_in_state(STATE))
+            .add_system(hint_system.run_in_state(STATE).after(CartSystem::MoveCart))
             .add_system(zip_label_system.run_in_state(STATE))
             .add_system(cart_color_system.run_in_state(STATE))
             .add_system(zip_label_color_system.run_in_state(STATE))
@@ -110,11 +121,29 @@ struct DrawlingPixel {
 }
 
 #[derive(Debug, Resource)]
-struct SetPixels(Grid<bool>);
+struct SetPixels {
+    grid: BitGrid,
+    contours: Contours,
+}
 
 #[derive(Debug, Resource)]
 struct FindZipPathTask(Task<Option<ZipPath>>);
 
+/// What [`FindZipPathTask::spawn`] found
+#[derive(Debug)]
+enum ZipSearch {
+    /// The cart can already drive to the next pixel
+    Reachable,
+    Started(FindZipPathTask),
+    /// The cart isn't on the ground, such as while it's zipping, so which
+    /// loop it's on isn't known yet
+    OffGround,
+}
+
+/// Starts the zip search again once the cart is back on the ground
+#[derive(Debug, Resource)]
+struct RetryZipSearch;
+
 #[derive(Debug, Clone, Copy, Resource)]
 struct ZipPath {
     start: Edge,
@@ -130,6 +159,9 @@ struct ZipLabelData {
 #[derive(Debug, Component)]
 struct ZipLabel;
 
+#[derive(Debug, Component)]
+struct HintLine;
+
 type WithCartOrPiece = Or<(With<Cart>, With<CartPiece>)>;
 
 type WithChangedCartOnly = (
@@ -163,6 +195,58 @@ type WithDrawlingPixelOnly = (
     With<DrawlingPixel>,
 );
 
+impl Cart {
+    /// The edge the cart is on, or the left side of the corner it is on
+    fn edge(&self) -> Edge {
+        match *self {
+            Cart::OnSide { pixel, side, .. }
+            | Cart::Falling { pixel, side, .. }
+            | Cart::Zipping { pixel, side, .. } => Edge::new(pixel, side),
+            Cart::OnOutsideCorner { pixel, corner, .. } => {
+                Edge::new(pixel, corner.rotate_left_side())
+            }
+            Cart::OnInsideCorner { pixel, corner, .. } => {
+                let side = corner.rotate_left_side();
+                Edge::new(
+                    (pixel.as_ivec2() + side.art_direction()).as_uvec2(),
+                    side.flip(),
+                )
+            }
+        }
+    }
+}
+
+impl SetPixels {
+    /// Adds `pos` to the ground
+    fn set(&mut self, pos: UVec2) {
+        if !self.grid.get(pos) {
+            self.grid.set(pos, true);
+            self.contours.place(&self.grid, pos);
+        }
+    }
+
+    /// Takes `positions` out of the ground, such as when the next frame
+    /// makes them transparent
+    fn remove(&mut self, positions: &[UVec2]) {
+        for &pos in positions {
+            self.grid.set(pos, false);
+        }
+        self.contours = Contours::new(&self.grid);
+    }
+
+    /// Where the cart ends up if it's falling from `edge`
+    fn landing_edge(&self, mut edge: Edge) -> Edge {
+        while let Some(pos) = self
+            .grid
+            .offset(edge.pos, edge.side.art_direction())
+            .filter(|&pos| self.grid.get(pos))
+        {
+            edge.pos = pos;
+        }
+        edge
+    }
+}
+
 impl Edge {
     fn cart_world_pos(self) -> Vec2 {
         world_pos(self.pos) + self.side.world_direction() * (1.0 + CART_HEIGHT) * 0.5
@@ -202,9 +286,12 @@ fn enter_system(
 
     NextPixel::spawn(pixel, &mut commands);
 
-    let mut grid = Grid::new(art.size());
-    grid[pixel.pos] = true;
-    commands.insert_resource(SetPixels(grid));
+    let mut set_pixels = SetPixels {
+        grid: BitGrid::new(art.size()),
+        contours: Contours::default(),
+    };
+    set_pixels.set(pixel.pos);
+    commands.insert_resource(set_pixels);
 
     commands.spawn((
         SpriteBundle {
@@ -261,23 +348,9 @@ fn move_cart_system(
     keys: Res<Input<KeyCode>>,
     time: Res<Time>,
     ordering: Res<CurrentOrdering>,
-    art: Res<Art>,
     zip_path: Option<Res<ZipPath>>,
 ) {
-    fn checked_add(a: UVec2, b: IVec2) -> Option<UVec2> {
-        a.x.checked_add_signed(b.x)
-            .zip(a.y.checked_add_signed(b.y))
-            .map(Into::into)
-    }
-
-    let checked_add_inside =
-        |a, b| checked_add(a, b).filter(|pos| pos.x < art.width() && pos.y < art.height());
-
-    let ground_set = |pos, offset| {
-        checked_add_inside(pos, offset)
-            .map(|pos| ground.0[pos])
-            .unwrap_or(false)
-    };
+    let ground_set = |pos, offset| ground.grid.get_offset(pos, offset);
 
     let get_direction = || match (keys.any_pressed(LEFT_KEYS), keys.any_pressed(RIGHT_KEYS)) {
         (true, false) => Some(-1.0),
@@ -309,7 +382,8 @@ fn move_cart_system(
                         }
                     }
                     if let Some(next_pixel) = ordering.peek() {
-                        if Some(next_pixel.pos) == checked_add(*pixel, side.art_direction()) {
+                        if Some(next_pixel.pos) == ground.grid.offset(*pixel, side.art_direction())
+                        {
                             commands.spawn((
                                 SpriteBundle {
                                     sprite: Sprite {
@@ -342,7 +416,7 @@ fn move_cart_system(
                             side.art_direction() + side.rotate_left().art_direction(),
                         ) {
                             *cart = Cart::OnInsideCorner {
-                                pixel: checked_add(*pixel, side.art_direction()).unwrap(),
+                                pixel: ground.grid.offset(*pixel, side.art_direction()).unwrap(),
                                 corner: side.flip().rotate_right_corner(),
                                 distance: -*distance,
                             };
@@ -353,7 +427,7 @@ fn move_cart_system(
                         side.art_direction() + side.rotate_right().art_direction(),
                     ) {
                         *cart = Cart::OnInsideCorner {
-                            pixel: checked_add(*pixel, side.art_direction()).unwrap(),
+                            pixel: ground.grid.offset(*pixel, side.art_direction()).unwrap(),
                             corner: side.flip().rotate_left_corner(),
                             distance: 1.0 - CART_HEIGHT - *distance,
                         };
@@ -364,7 +438,7 @@ fn move_cart_system(
                     let dir = side.rotate_left().art_direction();
                     if ground_set(*pixel, dir) {
                         if *distance < -0.5 {
-                            *pixel = checked_add(*pixel, dir).unwrap();
+                            *pixel = ground.grid.offset(*pixel, dir).unwrap();
                             *distance += 1.0;
                         }
                     } else {
@@ -378,7 +452,7 @@ fn move_cart_system(
                     let dir = side.rotate_right().art_direction();
                     if ground_set(*pixel, dir) {
                         if *distance > 0.5 {
-                            *pixel = checked_add(*pixel, dir).unwrap();
+                            *pixel = ground.grid.offset(*pixel, dir).unwrap();
                             *distance -= 1.0;
                         }
                     } else {
@@ -432,7 +506,7 @@ fn move_cart_system(
                     } else {
                         let side = corner.rotate_left_side();
                         *cart = Cart::OnSide {
-                            pixel: checked_add(*pixel, side.art_direction()).unwrap(),
+                            pixel: ground.grid.offset(*pixel, side.art_direction()).unwrap(),
                             side: side.flip(),
                             distance: -*distance,
                         };
@@ -447,7 +521,7 @@ fn move_cart_system(
                     } else {
                         let side = corner.rotate_right_side();
                         *cart = Cart::OnSide {
-                            pixel: checked_add(*pixel, side.art_direction()).unwrap(),
+                            pixel: ground.grid.offset(*pixel, side.art_direction()).unwrap(),
                             side: side.flip(),
                             distance: 1.0 - CART_HEIGHT - *distance,
                         };
@@ -467,10 +541,10 @@ fn move_cart_system(
             *offset += *velocity * time.delta_seconds();
             if *offset > 1.0 {
                 let direction = side.art_direction();
-                *pixel = checked_add_inside(*pixel, direction).unwrap();
+                *pixel = ground.grid.offset(*pixel, direction).unwrap();
                 let above_set = ground_set(*pixel, direction);
                 if !*finished_drawling {
-                    ground.0[*pixel] = true;
+                    ground.set(*pixel);
                     *finished_drawling = true;
                 }
                 if !above_set {
@@ -493,7 +567,7 @@ fn move_cart_system(
         } => {
             *t += (time.delta_seconds() * ZIP_SPEED) / start.distance(*end).sqrt();
             if *t > 1.0 {
-                ground.0[*pixel] = true;
+                ground.set(*pixel);
                 let next_pixel = ordering.peek().unwrap();
                 commands.remove_resource::<ZipPath>();
                 if *pixel == next_pixel.pos {
@@ -676,12 +750,11 @@ fn draw_pixel_system(
         WithDrawlingPixelOnly,
     >,
     cart_query: Query<&Cart, WithCartOnly>,
-    mesh_query: Query<&MulticolorMesh>,
+    mut mesh_query: Query<&mut MulticolorMesh>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut ordering: ResMut<CurrentOrdering>,
     next_pixel_query: Query<(&mut NextPixel, &mut Sprite, Entity), WithNextPixelOnly>,
     set_pixels: Res<SetPixels>,
-    art: Res<Art>,
 ) {
     if let Ok((pixel, mut pixel_transform, mut pixel_sprite, entity)) = pixel_query.get_single_mut()
     {
@@ -698,161 +771,322 @@ fn draw_pixel_system(
         };
         if finished {
             commands.entity(entity).despawn();
-            mesh_query
-                .single()
-                .edit(&mut meshes)
-                .add_next_from_ordering(&mut ordering);
+            let mut mesh = mesh_query.single_mut();
+            let mut editor = mesh.edit(&mut meshes);
+            editor.add_next_from_ordering(&mut ordering);
+            add_set_pixels(&mut editor, &mut ordering, &set_pixels);
             NextPixel::show_current(&mut commands, next_pixel_query, &ordering).unwrap();
-            if let Some(next_pixel) = ordering.peek() {
-                let next_pixel = next_pixel.pos;
-                let set_pixels = set_pixels.0.clone();
-                let art_size = art.size();
-                let current_edge = if let Cart::OnSide { pixel, side, .. }
-                | Cart::Falling { pixel, side, .. }
-                | Cart::Zipping { pixel, side, .. } = *cart
-                {
-                    Edge::new(pixel, side)
-                } else {
-                    unreachable!()
-                };
-                commands.insert_resource(FindZipPathTask(AsyncComputeTaskPool::get().spawn(
-                    async move {
-                        fn checked_add(a: UVec2, b: IVec2) -> Option<UVec2> {
-                            a.x.checked_add_signed(b.x)
-                                .zip(a.y.checked_add_signed(b.y))
-                                .map(Into::into)
-                        }
+            FindZipPathTask::replace(
+                &mut commands,
+                ordering.peek().map(|pixel| pixel.pos),
+                cart.edge(),
+                &set_pixels,
+            );
+        } else {
+            pixel_transform.translation =
+                (pixel.start_pos + pixel.grow_dir.world_direction() * dist * 0.5).extend(0.0);
+            pixel_sprite.custom_size = Some(match pixel.grow_dir {
+                Side::Top | Side::Bottom => Vec2::new(1.0, dist),
+                Side::Left | Side::Right => Vec2::new(dist, 1.0),
+            });
+        }
+    }
+}
 
-                        let checked_add_inside = |a, b| {
-                            checked_add(a, b).filter(|pos| pos.x < art_size.x && pos.y < art_size.y)
-                        };
+impl FindZipPathTask {
+    /// Starts looking for a zip path to `next_pixel`, cancelling the last
+    /// search by dropping its task so it can't finish with a stale path
+    fn replace(
+        commands: &mut Commands,
+        next_pixel: Option<UVec2>,
+        cart_edge: Edge,
+        set_pixels: &SetPixels,
+    ) {
+        commands.remove_resource::<ZipPath>();
+        commands.remove_resource::<Self>();
+        commands.remove_resource::<RetryZipSearch>();
+        let Some(next_pixel) = next_pixel else {
+            return;
+        };
+        match Self::spawn(next_pixel, cart_edge, set_pixels) {
+            ZipSearch::Reachable => {}
+            ZipSearch::Started(task) => commands.insert_resource(task),
+            ZipSearch::OffGround => commands.insert_resource(RetryZipSearch),
+        }
+    }
 
-                        let ground_set = |pos, offset| {
-                            checked_add_inside(pos, offset)
-                                .map(|pos| set_pixels[pos])
-                                .unwrap_or(false)
-                        };
+    /// Finds the edges the cart could zip between.  Only finding the
+    /// closest pair is left to the task, which compares every pair.  That's
+    /// fine for the loops of art this size, and it's off the main thread
+    fn spawn(next_pixel: UVec2, cart_edge: Edge, set_pixels: &SetPixels) -> ZipSearch {
+        let contours = &set_pixels.contours;
+        let Some(cart_contour) = contours.contour(set_pixels.landing_edge(cart_edge)) else {
+            return ZipSearch::OffGround;
+        };
+        let placing: Vec<_> = placing_edges(&set_pixels.grid, next_pixel).collect();
+        let targets: Vec<_> = if placing.is_empty() {
+            Side::SIDES.map(|side| Edge::new(next_pixel, side)).to_vec()
+        } else {
+            let mut ids: Vec<_> = placing
+                .iter()
+                .filter_map(|&edge| contours.contour(edge))
+                .collect();
+            if ids.contains(&cart_contour) {
+                return ZipSearch::Reachable;
+            }
+            ids.sort_unstable();
+            ids.dedup();
+            ids.into_iter().flat_map(|id| contours.edges(id)).collect()
+        };
+        let starts: Vec<_> = contours.edges(cart_contour).collect();
+
+        ZipSearch::Started(Self(AsyncComputeTaskPool::get().spawn(async move {
+            let targets: Vec<_> = targets
+                .into_iter()
+                .map(|edge| (edge, edge.cart_world_pos()))
+                .collect();
+            starts
+                .into_iter()
+                .flat_map(|start| {
+                    let pos = start.cart_world_pos();
+                    targets
+                        .iter()
+                        .map(move |&(end, end_pos)| (start, end, pos.distance_squared(end_pos)))
+                })
+                .min_by(|(_, _, dist1), (_, _, dist2)| dist1.total_cmp(dist2))
+                .filter(|(start, end, _)| start != end)
+                .map(|(start, end, _)| ZipPath { start, end })
+        })))
+    }
+}
 
-                        let edge_neighbors = |edge: Edge| {
-                            let traversable = |dir| {
-                                let top_offset = edge.side.art_direction()
-                                    + edge.side.rotate(dir).art_direction();
-                                if ground_set(edge.pos, top_offset) {
-                                    Edge::new(
-                                        checked_add(edge.pos, top_offset).unwrap(),
-                                        edge.side.rotate(!dir),
-                                    )
-                                } else {
-                                    let bottom_offset = edge.side.rotate(dir).art_direction();
-                                    if ground_set(edge.pos, bottom_offset) {
-                                        Edge::new(
-                                            checked_add(edge.pos, bottom_offset).unwrap(),
-                                            edge.side,
-                                        )
-                                    } else {
-                                        Edge::new(edge.pos, edge.side.rotate(dir))
-                                    }
-                                }
-                            };
+/// The edges of the ground the cart can place `next_pixel` from
+fn placing_edges(set_pixels: &BitGrid, next_pixel: UVec2) -> impl Iterator<Item = Edge> + '_ {
+    Side::SIDES.into_iter().filter_map(move |side| {
+        set_pixels
+            .offset(next_pixel, side.art_direction())
+            .filter(|&pos| set_pixels.get(pos))
+            .map(|pos| Edge::new(pos, side.flip()))
+    })
+}
 
-                            [traversable(LeftRight::Left), traversable(LeftRight::Right)]
-                                .into_iter()
-                        };
+/// The path shown by [`hint_system`], kept while the cart follows it
+#[derive(Debug)]
+struct Hint {
+    target: UVec2,
+    /// From the edge the cart is on to where it can place the target, or
+    /// to the start of the zip if it can't drive there.  Empty if neither
+    nodes: Vec<Edge>,
+}
 
-                        let search = move |poses: &[(Edge, Vec2)], mut queue: VecDeque<_>| {
-                            queue.clear();
-                            queue.push_back((current_edge, 0));
-
-                            let mut best_start_edge = current_edge;
-                            let mut best_end_edge = current_edge;
-                            let mut best_dist = f32::INFINITY;
-
-                            crate::graph::bfs(
-                                &mut queue,
-                                &mut HashSet::<Edge>::default(),
-                                |edge, _| {
-                                    let world_pos = edge.cart_world_pos();
-                                    let (end_edge, dist) = poses
-                                        .iter()
-                                        .map(|&(edge, pos)| (edge, world_pos.distance_squared(pos)))
-                                        .min_by(|(_, pos1), (_, pos2)| pos1.total_cmp(pos2))
-                                        .unwrap();
-                                    if dist < best_dist {
-                                        best_dist = dist;
-                                        best_start_edge = edge;
-                                        best_end_edge = end_edge;
-                                    }
-                                    enum Never {}
-                                    ControlFlow::<Never, Edge>::Continue(edge)
-                                },
-                                edge_neighbors,
-                            );
+/// Shows the cheapest way along the ground to where the next pixel can be
+/// placed while a hint key is held.  The path is only searched for again
+/// when the ground or the next pixel changes, or the cart leaves it
+#[allow(clippy::too_many_arguments)]
+fn hint_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    cart_query: Query<&Cart, WithCartOnly>,
+    hint_query: Query<Entity, With<HintLine>>,
+    set_pixels: Res<SetPixels>,
+    ordering: Res<CurrentOrdering>,
+    zip_path: Option<Res<ZipPath>>,
+    mut hint: Local<Option<Hint>>,
+) {
+    let target = ordering.peek().map(|pixel| pixel.pos);
+    let (Some(target), true) = (target, keys.any_pressed(HINT_KEYS)) else {
+        *hint = None;
+        for entity in &hint_query {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
 
-                            (best_start_edge != best_end_edge).then_some(ZipPath {
-                                start: best_start_edge,
-                                end: best_end_edge,
-                            })
-                        };
+    let start = set_pixels.landing_edge(cart_query.single().edge());
+    let outdated = keys.any_just_pressed(HINT_KEYS)
+        || set_pixels.is_changed()
+        || zip_path
+            .as_ref()
+            .is_some_and(|zip_path| zip_path.is_changed());
+    let progress = hint
+        .as_ref()
+        .filter(|hint| !outdated && hint.target == target)
+        .and_then(|hint| hint.nodes.iter().position(|&edge| edge == start));
+    match (&mut *hint, progress) {
+        (_, Some(0)) => return,
+        (Some(hint), Some(progress)) => {
+            hint.nodes.drain(..progress);
+        }
+        _ => {
+            *hint = Some(Hint {
+                target,
+                nodes: hint_path(start, target, &set_pixels)
+                    .or_else(|| {
+                        // Any way round the loop leads to the zip, so the
+                        // fewest edges will do
+                        let zip_start = zip_path?.start;
+                        graph::bfs_path(
+                            start,
+                            &mut HashSet::new(),
+                            |edge| edge == zip_start,
+                            |edge| edge_neighbors(&set_pixels.grid, edge),
+                        )
+                    })
+                    .unwrap_or_default(),
+            });
+        }
+    }
 
-                        let mut start_edges = VecDeque::with_capacity(8);
-                        if next_pixel.y > 0 && set_pixels[next_pixel - UVec2::Y] {
-                            start_edges
-                                .push_back((Edge::new(next_pixel - UVec2::Y, Side::Bottom), 0));
-                        };
-                        if next_pixel.y < art_size.y - 1 && set_pixels[next_pixel + UVec2::Y] {
-                            start_edges.push_back((Edge::new(next_pixel + UVec2::Y, Side::Top), 0));
-                        }
-                        if next_pixel.x > 0 && set_pixels[next_pixel - UVec2::X] {
-                            start_edges
-                                .push_back((Edge::new(next_pixel - UVec2::X, Side::Right), 0));
-                        }
-                        if next_pixel.x < art_size.x - 1 && set_pixels[next_pixel + UVec2::X] {
-                            start_edges
-                                .push_back((Edge::new(next_pixel + UVec2::X, Side::Left), 0));
-                        }
+    for entity in &hint_query {
+        commands.entity(entity).despawn();
+    }
+    for pair in hint.as_ref().unwrap().nodes.windows(2) {
+        Line {
+            points: [pair[0].cart_world_pos(), pair[1].cart_world_pos()],
+            color: ColorType::Secondary,
+        }
+        .spawn(&mut commands, 0.5)
+        .insert(HintLine);
+    }
+}
 
-                        if start_edges.is_empty() {
-                            let poses = Side::SIDES
-                                .map(|side| Edge::new(next_pixel, side))
-                                .map(|edge| (edge, edge.cart_world_pos()));
-                            search(&poses, start_edges)
-                        } else {
-                            let mut connected = HashSet::<Edge>::new();
-                            let connected_to_start = crate::graph::bfs(
-                                &mut start_edges,
-                                &mut connected,
-                                |edge, _| {
-                                    if edge == current_edge {
-                                        ControlFlow::Break(())
-                                    } else {
-                                        ControlFlow::Continue(edge)
-                                    }
-                                },
-                                edge_neighbors,
-                            );
-                            connected_to_start
-                                .is_none()
-                                .then(|| {
-                                    let poses: Vec<_> = connected
-                                        .into_iter()
-                                        .map(|edge| (edge, edge.cart_world_pos()))
-                                        .collect();
-                                    search(&poses, start_edges)
-                                })
-                                .flatten()
-                        }
-                    },
-                )))
-            }
-        } else {
-            pixel_transform.translation =
-                (pixel.start_pos + pixel.grow_dir.world_direction() * dist * 0.5).extend(0.0);
-            pixel_sprite.custom_size = Some(match pixel.grow_dir {
-                Side::Top | Side::Bottom => Vec2::new(1.0, dist),
-                Side::Left | Side::Right => Vec2::new(dist, 1.0),
+/// The cheapest way along the ground from `start` to where `target` can be
+/// placed, or `None` if they aren't on the same loop of the ground
+fn hint_path(start: Edge, target: UVec2, set_pixels: &SetPixels) -> Option<Vec<Edge>> {
+    let contour = set_pixels.contours.contour(start)?;
+    let goals: Vec<_> = placing_edges(&set_pixels.grid, target)
+        .filter(|&edge| set_pixels.contours.contour(edge) == Some(contour))
+        .map(|edge| (edge, edge.cart_world_pos()))
+        .collect();
+    if goals.is_empty() {
+        return None;
+    }
+    let path = graph::a_star(
+        start,
+        &mut HashSet::new(),
+        |edge| goals.iter().any(|&(goal, _)| goal == edge),
+        |edge| edge_neighbors(&set_pixels.grid, edge),
+        |from: Edge, to: Edge| from.cart_world_pos().distance(to.cart_world_pos()),
+        |edge| {
+            let pos = edge.cart_world_pos();
+            goals
+                .iter()
+                .map(|&(_, goal)| pos.distance(goal))
+                .fold(f32::INFINITY, f32::min)
+        },
+    )?;
+    Some(path.nodes)
+}
+
+/// Adds pixels that are already part of the ground straight to the mesh,
+/// because the cart has no way to drive over them
+fn add_set_pixels(
+    editor: &mut MulticolorMeshEditor,
+    ordering: &mut CurrentOrdering,
+    set_pixels: &SetPixels,
+) {
+    while ordering
+        .peek()
+        .filter(|pixel| set_pixels.grid.get(pixel.pos))
+        .is_some()
+    {
+        editor.add_next_from_ordering(ordering);
+    }
+}
+
+/// Takes pixels the new frame made transparent out of the ground, moving the
+/// cart off them, and carries on with the pixels the new frame added
+#[allow(clippy::too_many_arguments)]
+fn next_frame_system(
+    mut commands: Commands,
+    mut frame_events: EventReader<FrameAdvanced>,
+    mut cart_query: Query<&mut Cart, WithCartOnly>,
+    mut mesh_query: Query<&mut MulticolorMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut ordering: ResMut<CurrentOrdering>,
+    mut set_pixels: ResMut<SetPixels>,
+) {
+    let mut advanced = false;
+    let mut removed = Vec::new();
+    for event in frame_events.iter() {
+        advanced = true;
+        removed.extend_from_slice(&event.removed);
+    }
+    if !advanced {
+        return;
+    }
+
+    let mut cart = cart_query.single_mut();
+    if !removed.is_empty() {
+        set_pixels.remove(&removed);
+        let on_ground =
+            matches!(*cart, Cart::Zipping { .. }) || set_pixels.grid.get(cart.edge().pos);
+        if !on_ground {
+            let from = cart.edge().pos.as_vec2();
+            let landing = set_pixels.contours.all_edges().min_by(|a, b| {
+                let a = a.pos.as_vec2().distance_squared(from);
+                let b = b.pos.as_vec2().distance_squared(from);
+                a.total_cmp(&b)
             });
+            match landing {
+                Some(edge) => {
+                    *cart = Cart::OnSide {
+                        pixel: edge.pos,
+                        side: edge.side,
+                        distance: 0.0,
+                    };
+                }
+                // Nothing is left to drive on, so the next pixel is placed
+                // straight away, like the first one
+                None => {
+                    if let Some(next_pixel) = ordering.peek() {
+                        set_pixels.set(next_pixel.pos);
+                        *cart = Cart::OnSide {
+                            pixel: next_pixel.pos,
+                            side: Side::Top,
+                            distance: 0.0,
+                        };
+                    }
+                }
+            }
         }
     }
+
+    add_set_pixels(
+        &mut mesh_query.single_mut().edit(&mut meshes),
+        &mut ordering,
+        &set_pixels,
+    );
+    if let Some(next_pixel) = ordering.peek() {
+        NextPixel::spawn(next_pixel, &mut commands);
+    }
+    FindZipPathTask::replace(
+        &mut commands,
+        ordering.peek().map(|pixel| pixel.pos),
+        cart.edge(),
+        &set_pixels,
+    );
+}
+
+fn retry_zip_search_system(
+    mut commands: Commands,
+    cart_query: Query<&Cart, WithCartOnly>,
+    ordering: Res<CurrentOrdering>,
+    set_pixels: Res<SetPixels>,
+) {
+    let cart_edge = cart_query.single().edge();
+    if set_pixels
+        .contours
+        .contour(set_pixels.landing_edge(cart_edge))
+        .is_some()
+    {
+        FindZipPathTask::replace(
+            &mut commands,
+            ordering.peek().map(|pixel| pixel.pos),
+            cart_edge,
+            &set_pixels,
+        );
+    }
 }
 
 fn zip_system(mut commands: Commands, mut task: ResMut<FindZipPathTask>) {
@@ -945,6 +1179,7 @@ fn exit_system(
             With<DrawlingPixel>,
             With<MulticolorMesh>,
             With<ZipLabel>,
+            With<HintLine>,
         )>,
     >,
 ) {
@@ -954,6 +1189,7 @@ fn exit_system(
     commands.remove_resource::<SetPixels>();
     commands.remove_resource::<ZipLabelData>();
     commands.remove_resource::<FindZipPathTask>();
+    commands.remove_resource::<RetryZipSearch>();
 }
 
 fn crossed<T: PartialOrd>(bound: T, a: T, b: T) -> bool {
@@ -0,0 +1,398 @@
+//! The exposed edges of the ground, split into the loops the cart can drive
+//! around, and kept up to date as pixels are placed
+
+use std::mem;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    bit_grid::BitGrid,
+    side::{Edge, LeftRight, Side},
+};
+
+/// Which loop each exposed edge is on.  The cart can drive from one edge to
+/// another exactly when they're on the same loop
+#[derive(Debug, Default)]
+pub struct Contours {
+    ids: HashMap<Edge, usize>,
+    contours: HashMap<usize, HashSet<Edge>>,
+    next_id: usize,
+}
+
+impl Contours {
+    /// Finds every loop in `grid` from scratch, such as after pixels are
+    /// removed, which [`Self::place`] can't undo
+    pub fn new(grid: &BitGrid) -> Self {
+        let mut contours = Self::default();
+        let size = grid.size();
+        let edges = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .flat_map(|pos| Side::SIDES.map(|side| Edge::new(pos, side)));
+        for start in edges {
+            if !is_exposed(grid, start) || contours.ids.contains_key(&start) {
+                continue;
+            }
+            let id = contours.new_contour();
+            let mut edge = start;
+            loop {
+                contours.ids.insert(edge, id);
+                contours.contours.get_mut(&id).unwrap().insert(edge);
+                edge = edge_neighbors(grid, edge)[1];
+                if edge == start {
+                    break;
+                }
+            }
+        }
+        contours
+    }
+
+    /// The loop `edge` is on, if it's exposed
+    pub fn contour(&self, edge: Edge) -> Option<usize> {
+        self.ids.get(&edge).copied()
+    }
+
+    /// Every exposed edge, on any loop
+    pub fn all_edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.ids.keys().copied()
+    }
+
+    pub fn edges(&self, id: usize) -> impl Iterator<Item = Edge> + '_ {
+        self.contours[&id].iter().copied()
+    }
+
+    /// Updates the loops after `pos` is set in `grid`.  Only the edges
+    /// around `pos` can change neighbors, so the loops through them are
+    /// walked in step until all but one are closed.  The last one keeps the
+    /// edges it didn't get to, which makes placing a pixel cost about as
+    /// much as the smaller loops it touches
+    pub fn place(&mut self, grid: &BitGrid, pos: UVec2) {
+        let local: Vec<_> = (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| IVec2::new(x, y)))
+            .filter_map(|offset| grid.offset(pos, offset))
+            .flat_map(|pos| Side::SIDES.map(|side| Edge::new(pos, side)))
+            .collect();
+
+        // Every loop that went past `pos` is merged into the biggest of
+        // them, and the others are split back out as they close
+        let mut touched: Vec<_> = local
+            .iter()
+            .filter_map(|&edge| self.contour(edge))
+            .collect();
+        touched.sort_unstable();
+        touched.dedup();
+        let merged = match touched
+            .iter()
+            .copied()
+            .max_by_key(|id| self.contours[id].len())
+        {
+            Some(id) => id,
+            None => self.new_contour(),
+        };
+        for id in touched {
+            if id != merged {
+                let edges = self.contours.remove(&id).unwrap();
+                for &edge in &edges {
+                    self.ids.insert(edge, merged);
+                }
+                self.contours.get_mut(&merged).unwrap().extend(edges);
+            }
+        }
+        for edge in &local {
+            if self.ids.remove(edge).is_some() {
+                self.contours.get_mut(&merged).unwrap().remove(edge);
+            }
+        }
+
+        let starts: Vec<_> = local
+            .into_iter()
+            .filter(|&edge| is_exposed(grid, edge))
+            .collect();
+        let claims: HashMap<_, _> = starts
+            .iter()
+            .enumerate()
+            .map(|(index, &edge)| (edge, index))
+            .collect();
+        let mut walkers: Vec<_> = starts
+            .iter()
+            .map(|&edge| Walker {
+                current: edge,
+                edges: vec![edge],
+                walking: true,
+            })
+            .collect();
+        // Walkers that reach another's start are on the same loop, and hand
+        // their edges over to it
+        let mut owners: Vec<_> = (0..walkers.len()).collect();
+        let mut walking = walkers.len();
+        while walking > 1 {
+            for index in 0..walkers.len() {
+                if !walkers[index].walking {
+                    continue;
+                }
+                let next = edge_neighbors(grid, walkers[index].current)[1];
+                let Some(&claim) = claims.get(&next) else {
+                    walkers[index].current = next;
+                    walkers[index].edges.push(next);
+                    continue;
+                };
+                walkers[index].walking = false;
+                walking -= 1;
+                let edges = mem::take(&mut walkers[index].edges);
+                let owner = find_owner(&mut owners, claim);
+                if owner == index {
+                    let id = self.new_contour();
+                    self.assign(id, edges);
+                } else {
+                    owners[index] = owner;
+                    walkers[owner].edges.extend(edges);
+                }
+            }
+        }
+
+        if let Some(walker) = walkers.into_iter().find(|walker| walker.walking) {
+            self.assign(merged, walker.edges);
+        }
+        if self.contours[&merged].is_empty() {
+            self.contours.remove(&merged);
+        }
+    }
+
+    fn new_contour(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.contours.insert(id, HashSet::new());
+        id
+    }
+
+    /// Moves `edges` to the loop `id`
+    fn assign(&mut self, id: usize, edges: impl IntoIterator<Item = Edge>) {
+        for edge in edges {
+            if let Some(old) = self.ids.insert(edge, id).filter(|&old| old != id) {
+                self.contours.get_mut(&old).unwrap().remove(&edge);
+            }
+            self.contours.get_mut(&id).unwrap().insert(edge);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Walker {
+    current: Edge,
+    edges: Vec<Edge>,
+    walking: bool,
+}
+
+/// Follows `owners` to the walker that's still collecting the edges
+fn find_owner(owners: &mut [usize], mut index: usize) -> usize {
+    while owners[index] != index {
+        owners[index] = owners[owners[index]];
+        index = owners[index];
+    }
+    index
+}
+
+/// Whether the cart could drive along `edge`
+fn is_exposed(grid: &BitGrid, edge: Edge) -> bool {
+    grid.get(edge.pos) && !grid.get_offset(edge.pos, edge.side.art_direction())
+}
+
+/// The two edges the cart can drive to from `edge`, going left and right
+pub fn edge_neighbors(set_pixels: &BitGrid, edge: Edge) -> [Edge; 2] {
+    let traversable = |dir| {
+        let top_offset = edge.side.art_direction() + edge.side.rotate(dir).art_direction();
+        if let Some(top) = set_pixels
+            .offset(edge.pos, top_offset)
+            .filter(|&pos| set_pixels.get(pos))
+        {
+            Edge::new(top, edge.side.rotate(!dir))
+        } else {
+            let bottom_offset = edge.side.rotate(dir).art_direction();
+            if let Some(bottom) = set_pixels
+                .offset(edge.pos, bottom_offset)
+                .filter(|&pos| set_pixels.get(pos))
+            {
+                Edge::new(bottom, edge.side)
+            } else {
+                Edge::new(edge.pos, edge.side.rotate(dir))
+            }
+        }
+    };
+
+    [traversable(LeftRight::Left), traversable(LeftRight::Right)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, ops::ControlFlow};
+
+    use super::*;
+    use crate::graph;
+
+    const SIZE: UVec2 = UVec2::new(7, 7);
+
+    /// Sets each of `positions` in turn, checking the loops against a
+    /// search over every edge after each one
+    fn place_all(positions: &[(u32, u32)]) -> (BitGrid, Contours) {
+        let mut grid = BitGrid::new(SIZE);
+        let mut contours = Contours::default();
+        for &pos in positions {
+            let pos = UVec2::from(pos);
+            grid.set(pos, true);
+            contours.place(&grid, pos);
+            assert_matches_search(&grid, &contours);
+            assert_matches_search(&grid, &Contours::new(&grid));
+        }
+        (grid, contours)
+    }
+
+    fn assert_matches_search(grid: &BitGrid, contours: &Contours) {
+        let edges = (0..SIZE.y)
+            .flat_map(|y| (0..SIZE.x).map(move |x| UVec2::new(x, y)))
+            .flat_map(|pos| Side::SIDES.map(|side| Edge::new(pos, side)));
+        let mut marked = HashSet::new();
+        let mut loops = 0;
+        for start in edges {
+            if !is_exposed(grid, start) {
+                assert_eq!(contours.contour(start), None, "{start:?} isn't exposed");
+                continue;
+            }
+            if marked.contains(&start) {
+                continue;
+            }
+            let mut found = HashSet::new();
+            graph::bfs(
+                &mut VecDeque::from([(start, 0)]),
+                &mut marked,
+                |edge, _| {
+                    found.insert(edge);
+                    ControlFlow::<(), _>::Continue(edge)
+                },
+                |edge| edge_neighbors(grid, edge),
+            );
+            let id = contours.contour(start).expect("exposed edge has no loop");
+            assert_eq!(contours.edges(id).collect::<HashSet<_>>(), found);
+            loops += 1;
+        }
+        assert_eq!(contours.contours.len(), loops);
+    }
+
+    fn loop_count(positions: &[(u32, u32)]) -> usize {
+        place_all(positions).1.contours.len()
+    }
+
+    #[test]
+    fn ring_splits_off_the_hole() {
+        let ring = [
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (1, 2),
+        ];
+        assert_eq!(loop_count(&ring[..7]), 1);
+        assert_eq!(loop_count(&ring), 2);
+    }
+
+    #[test]
+    fn ring_against_the_border_splits_off_the_hole() {
+        let ring = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (1, 2),
+            (0, 2),
+            (0, 1),
+        ];
+        assert_eq!(loop_count(&ring), 2);
+    }
+
+    #[test]
+    fn diagonal_ring() {
+        place_all(&[(3, 1), (4, 2), (3, 3), (2, 2)]);
+    }
+
+    #[test]
+    fn filling_the_hole_removes_its_loop() {
+        let mut shape = vec![
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (1, 2),
+        ];
+        shape.push((2, 2));
+        assert_eq!(loop_count(&shape), 1);
+    }
+
+    #[test]
+    fn bridge_merges_islands() {
+        let islands = [(1, 3), (1, 2), (5, 3), (5, 4), (4, 4)];
+        assert_eq!(loop_count(&islands), 2);
+        let mut bridged = islands.to_vec();
+        bridged.extend([(2, 3), (3, 3)]);
+        assert_eq!(loop_count(&bridged), 1);
+    }
+
+    #[test]
+    fn bridge_closing_a_hole_merges_and_splits() {
+        place_all(&[
+            (1, 1),
+            (1, 2),
+            (1, 3),
+            (2, 3),
+            (3, 3),
+            (3, 1),
+            (3, 2),
+            (2, 1),
+        ]);
+    }
+
+    #[test]
+    fn rebuilding_after_removing_pixels() {
+        let (mut grid, _) = place_all(&[
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+            (1, 2),
+            (2, 2),
+        ]);
+        grid.set(UVec2::new(2, 2), false);
+        let contours = Contours::new(&grid);
+        assert_matches_search(&grid, &contours);
+        assert_eq!(contours.contours.len(), 2);
+
+        grid.set(UVec2::new(2, 1), false);
+        grid.set(UVec2::new(2, 3), false);
+        let contours = Contours::new(&grid);
+        assert_matches_search(&grid, &contours);
+        assert_eq!(contours.contours.len(), 2);
+    }
+
+    #[test]
+    fn random_placements() {
+        for seed in 0..20 {
+            let rng = fastrand::Rng::with_seed(seed);
+            let mut positions: Vec<_> = (0..SIZE.y)
+                .flat_map(|y| (0..SIZE.x).map(move |x| (x, y)))
+                .filter(|_| rng.u8(..3) != 0)
+                .collect();
+            rng.shuffle(&mut positions);
+            place_all(&positions);
+        }
+    }
+}
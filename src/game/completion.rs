@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{ArtFrames, ArtName, ArtPath},
+    camera::AreaTrackingProjection,
+    ordering::{CurrentOrdering, Orderings},
+    playlist::Playlist,
+    GameState,
+};
+
+/// How long the completion card shows before the next art in the
+/// playlist starts, in seconds
+const CARD_DURATION: f32 = 3.0;
+
+pub struct CompletionPlugin;
+
+impl Plugin for CompletionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            completion_system
+                .run_if(GameState::current_is_play)
+                .run_if_resource_exists::<CurrentOrdering>()
+                .run_if_resource_exists::<Playlist>(),
+        );
+    }
+}
+
+/// Shows a card once the ordering is finished, then starts the next art in
+/// the [`Playlist`] with the same ordering and game
+#[allow(clippy::too_many_arguments)]
+fn completion_system(
+    mut commands: Commands,
+    state: Res<CurrentState<GameState>>,
+    time: Res<Time>,
+    mut egui_context: ResMut<EguiContext>,
+    mut card_timer: Local<Option<Timer>>,
+    art_name: Res<ArtName>,
+    frames: Res<ArtFrames>,
+    mut orderings: ResMut<Orderings>,
+    mut ordering: ResMut<CurrentOrdering>,
+    mut playlist: ResMut<Playlist>,
+    mut projection_query: Query<&mut AreaTrackingProjection>,
+) {
+    // Animated art moves on to its next frame instead
+    if !ordering.is_finished() || (frames.animate && frames.has_next()) {
+        *card_timer = None;
+        return;
+    }
+    let timer =
+        card_timer.get_or_insert_with(|| Timer::from_seconds(CARD_DURATION, TimerMode::Once));
+    timer.tick(time.delta());
+
+    let has_next = playlist.has_next();
+    let mut next_clicked = false;
+    egui::Window::new("Finished!")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(&art_name.0);
+            ui.label(format!("{}/{}", playlist.position() + 1, playlist.len()));
+            if has_next {
+                ui.add(egui::ProgressBar::new(timer.percent()).text("Next art"));
+            }
+            ui.horizontal(|ui| {
+                if has_next && ui.button("Next").clicked() {
+                    next_clicked = true;
+                }
+                if ui.button("Menu").clicked() {
+                    commands.insert_resource(NextState(GameState::MainMenu));
+                }
+            });
+        });
+
+    if has_next && (next_clicked || timer.finished()) {
+        let entry = playlist.advance();
+        let art = entry.frames.current();
+        orderings.clear();
+        *ordering = CurrentOrdering::init(&mut orderings, ordering.typ(), ordering.options(), &art);
+        let mut projection = projection_query.single_mut();
+        projection.track_art(&art);
+        projection.fit();
+
+        commands.insert_resource(art);
+        commands.insert_resource(entry.frames.clone());
+        commands.insert_resource(ArtName(entry.name.clone()));
+        ArtPath::replace(&mut commands, entry.path.as_deref());
+        // Entering the same state again restarts the game
+        commands.insert_resource(NextState(state.0));
+        *card_timer = None;
+    }
+}
@@ -1,6 +1,11 @@
-use std::{collections::VecDeque, hash::Hash, ops::ControlFlow};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    hash::Hash,
+    ops::ControlFlow,
+};
 
-use bevy::utils::HashSet;
+use bevy::utils::{HashMap, HashSet};
 
 pub fn bfs<N, O>(
     queue: &mut VecDeque<(N, u32)>,
@@ -24,6 +29,143 @@ pub fn bfs<N, O>(
     None
 }
 
+/// Like [`bfs`] from just `start`, but remembers how each node was reached
+/// and returns the nodes from `start` to the first node where `is_goal` is
+/// true.  Pass `|node| node == target` to find a path to a single node
+pub fn bfs_path<N>(
+    start: N,
+    marked: &mut impl Set<N>,
+    mut is_goal: impl FnMut(N) -> bool,
+    mut neighbors: impl NeighborsFn<N>,
+) -> Option<Vec<N>>
+where
+    N: Copy + Eq + Hash,
+{
+    let mut parents = HashMap::new();
+    bfs(
+        &mut VecDeque::from([(start, 0)]),
+        marked,
+        |node, _| {
+            if is_goal(node) {
+                ControlFlow::Break(node)
+            } else {
+                ControlFlow::Continue(node)
+            }
+        },
+        |node| {
+            let neighbors: Vec<_> = neighbors.get(node).into_iter().collect();
+            for &neighbor in &neighbors {
+                if neighbor != start {
+                    parents.entry(neighbor).or_insert(node);
+                }
+            }
+            neighbors
+        },
+    )
+    .map(|end| reconstruct_path(&parents, end))
+}
+
+/// A route through a graph, from the start node to the end node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path<N> {
+    pub nodes: Vec<N>,
+    pub cost: f32,
+}
+
+/// Finds the cheapest path from `start` to a node where `is_goal` is true.
+/// `cost` gives the cost of going between two neighbors, and must not be
+/// negative.  `heuristic` estimates the cost from a node to the nearest
+/// goal, and must never overestimate it.  A heuristic of `|_| 0.0` makes
+/// this Dijkstra's algorithm
+pub fn a_star<N>(
+    start: N,
+    marked: &mut impl Set<N>,
+    mut is_goal: impl FnMut(N) -> bool,
+    mut neighbors: impl NeighborsFn<N>,
+    mut cost: impl FnMut(N, N) -> f32,
+    mut heuristic: impl FnMut(N) -> f32,
+) -> Option<Path<N>>
+where
+    N: Copy + Eq + Hash,
+{
+    let mut costs = HashMap::from_iter([(start, 0.0)]);
+    let mut parents = HashMap::new();
+    let mut queue = BinaryHeap::from([Queued {
+        estimate: heuristic(start),
+        node: start,
+    }]);
+    while let Some(Queued { node, .. }) = queue.pop() {
+        if !marked.insert(&node) {
+            continue;
+        }
+        if is_goal(node) {
+            return Some(Path {
+                nodes: reconstruct_path(&parents, node),
+                cost: costs[&node],
+            });
+        }
+        for neighbor in neighbors.get(node) {
+            if marked.contains(&neighbor) {
+                continue;
+            }
+            let neighbor_cost = costs[&node] + cost(node, neighbor);
+            let cheaper = match costs.get(&neighbor) {
+                Some(&old) => neighbor_cost < old,
+                None => true,
+            };
+            if cheaper {
+                costs.insert(neighbor, neighbor_cost);
+                parents.insert(neighbor, node);
+                queue.push(Queued {
+                    estimate: neighbor_cost + heuristic(neighbor),
+                    node: neighbor,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Follows `parents` back from `end` to the node without a parent, and
+/// returns the nodes from there to `end`
+pub fn reconstruct_path<N>(parents: &HashMap<N, N>, end: N) -> Vec<N>
+where
+    N: Copy + Eq + Hash,
+{
+    let mut nodes = vec![end];
+    while let Some(&parent) = parents.get(nodes.last().unwrap()) {
+        nodes.push(parent);
+    }
+    nodes.reverse();
+    nodes
+}
+
+/// A node in the [`a_star`] queue, which pops the lowest estimate first
+struct Queued<N> {
+    estimate: f32,
+    node: N,
+}
+
+impl<N> PartialEq for Queued<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<N> Eq for Queued<N> {}
+
+impl<N> PartialOrd for Queued<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for Queued<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
 pub trait Set<N> {
     fn insert(&mut self, node: &N) -> bool;
 
@@ -60,3 +202,245 @@ where
         self(node)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::*;
+    use crate::side::{Edge, Side};
+
+    /// The loop of edges around two pixels side by side, clockwise from the
+    /// top of the left one
+    fn loop_2x1() -> [Edge; 6] {
+        [
+            Edge::new(UVec2::new(0, 0), Side::Top),
+            Edge::new(UVec2::new(1, 0), Side::Top),
+            Edge::new(UVec2::new(1, 0), Side::Right),
+            Edge::new(UVec2::new(1, 0), Side::Bottom),
+            Edge::new(UVec2::new(0, 0), Side::Bottom),
+            Edge::new(UVec2::new(0, 0), Side::Left),
+        ]
+    }
+
+    /// The edges before and after `edge` on its loop.  Edges that aren't
+    /// around the two pixels are around a pixel of their own
+    fn neighbors(edge: Edge) -> [Edge; 2] {
+        let edges = loop_2x1();
+        match edges.iter().position(|&other| other == edge) {
+            Some(index) => [edges[(index + 5) % 6], edges[(index + 1) % 6]],
+            None => [
+                Edge::new(edge.pos, edge.side.rotate_left()),
+                Edge::new(edge.pos, edge.side.rotate_right()),
+            ],
+        }
+    }
+
+    fn path_to(start: Edge, goal: Edge) -> Option<Vec<Edge>> {
+        bfs_path(start, &mut HashSet::new(), |edge| edge == goal, neighbors)
+    }
+
+    #[test]
+    fn bfs_path_goes_the_short_way_round() {
+        let edges = loop_2x1();
+        assert_eq!(
+            path_to(edges[0], edges[2]),
+            Some(vec![edges[0], edges[1], edges[2]])
+        );
+        assert_eq!(
+            path_to(edges[0], edges[4]),
+            Some(vec![edges[0], edges[5], edges[4]])
+        );
+    }
+
+    #[test]
+    fn bfs_path_stops_at_first_goal() {
+        let edges = loop_2x1();
+        let path = bfs_path(
+            edges[1],
+            &mut HashSet::new(),
+            |edge| edge.side == Side::Bottom,
+            neighbors,
+        );
+        assert_eq!(path, Some(vec![edges[1], edges[2], edges[3]]));
+    }
+
+    #[test]
+    fn bfs_path_to_start_is_just_start() {
+        let start = loop_2x1()[3];
+        assert_eq!(path_to(start, start), Some(vec![start]));
+    }
+
+    #[test]
+    fn bfs_path_to_other_loop_is_none() {
+        let mut marked = HashSet::new();
+        let path = bfs_path(
+            loop_2x1()[0],
+            &mut marked,
+            |edge| edge == Edge::new(UVec2::new(5, 5), Side::Top),
+            neighbors,
+        );
+        assert_eq!(path, None);
+        assert_eq!(marked, HashSet::from_iter(loop_2x1()));
+    }
+
+    /// Two ways from 0 to 4: an expensive edge then a cheap one, or three
+    /// cheap edges
+    const WEIGHTED: [(u32, u32, f32); 5] = [
+        (0, 1, 10.0),
+        (1, 4, 1.0),
+        (0, 2, 1.0),
+        (2, 3, 1.0),
+        (3, 4, 1.0),
+    ];
+
+    fn weighted_neighbors(node: u32) -> Vec<u32> {
+        WEIGHTED
+            .iter()
+            .filter_map(|&(a, b, _)| {
+                if node == a {
+                    Some(b)
+                } else if node == b {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn weighted_cost(from: u32, to: u32) -> f32 {
+        WEIGHTED
+            .iter()
+            .find(|&&(a, b, _)| (a, b) == (from, to) || (b, a) == (from, to))
+            .unwrap()
+            .2
+    }
+
+    #[test]
+    fn a_star_takes_cheapest_path() {
+        let path = a_star(
+            0,
+            &mut HashSet::new(),
+            |node| node == 4,
+            weighted_neighbors,
+            weighted_cost,
+            |_| 0.0,
+        );
+        assert_eq!(
+            path,
+            Some(Path {
+                nodes: vec![0, 2, 3, 4],
+                cost: 3.0,
+            })
+        );
+
+        // Fewer steps, but more expensive
+        let shortest = bfs_path(0, &mut HashSet::new(), |node| node == 4, weighted_neighbors);
+        assert_eq!(shortest, Some(vec![0, 1, 4]));
+    }
+
+    /// A 6x6 grid with a wall down the middle, open at the bottom
+    fn grid_neighbors(pos: UVec2) -> Vec<UVec2> {
+        let wall = |pos: UVec2| pos.x == 3 && pos.y < 5;
+        [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y]
+            .into_iter()
+            .map(|offset| pos.as_ivec2() + offset)
+            .filter(|pos| pos.cmpge(IVec2::ZERO).all() && pos.cmplt(IVec2::splat(6)).all())
+            .map(|pos| pos.as_uvec2())
+            .filter(|&pos| !wall(pos))
+            .collect()
+    }
+
+    #[test]
+    fn a_star_matches_bfs_and_dijkstra() {
+        let start = UVec2::ZERO;
+        let goal = UVec2::new(5, 0);
+        let unit = a_star(
+            start,
+            &mut HashSet::new(),
+            |pos| pos == goal,
+            grid_neighbors,
+            |_, _| 1.0,
+            |_| 0.0,
+        )
+        .unwrap();
+        let shortest = bfs_path(
+            start,
+            &mut HashSet::new(),
+            |pos| pos == goal,
+            grid_neighbors,
+        )
+        .unwrap();
+        assert_eq!(unit.nodes.len(), shortest.len());
+        assert_eq!(unit.cost, (shortest.len() - 1) as f32);
+
+        // Entering a pixel costs more the further down it is
+        let cost = |_, to: UVec2| 1.0 + to.y as f32;
+        let dijkstra = a_star(
+            start,
+            &mut HashSet::new(),
+            |pos| pos == goal,
+            grid_neighbors,
+            cost,
+            |_| 0.0,
+        )
+        .unwrap();
+        let manhattan = |pos: UVec2| {
+            let offset = (pos.as_ivec2() - goal.as_ivec2()).abs();
+            (offset.x + offset.y) as f32
+        };
+        let guided = a_star(
+            start,
+            &mut HashSet::new(),
+            |pos| pos == goal,
+            grid_neighbors,
+            cost,
+            manhattan,
+        )
+        .unwrap();
+        assert_eq!(guided.cost, dijkstra.cost);
+        assert_eq!(
+            guided.cost,
+            guided
+                .nodes
+                .windows(2)
+                .map(|pair| cost(pair[0], pair[1]))
+                .sum::<f32>()
+        );
+        assert_eq!(guided.nodes.first(), Some(&start));
+        assert_eq!(guided.nodes.last(), Some(&goal));
+    }
+
+    #[test]
+    fn a_star_to_start_is_free() {
+        let path = a_star(
+            2,
+            &mut HashSet::new(),
+            |node| node == 2,
+            weighted_neighbors,
+            weighted_cost,
+            |_| 0.0,
+        );
+        assert_eq!(
+            path,
+            Some(Path {
+                nodes: vec![2],
+                cost: 0.0,
+            })
+        );
+    }
+
+    #[test]
+    fn a_star_to_unreachable_goal_is_none() {
+        let path = a_star(
+            0,
+            &mut HashSet::new(),
+            |node| node == 7,
+            weighted_neighbors,
+            weighted_cost,
+            |_| 0.0,
+        );
+        assert_eq!(path, None);
+    }
+}
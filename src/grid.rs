@@ -5,6 +5,21 @@ use std::{
 
 use bevy::prelude::*;
 
+/// Offsets to the pixels sharing a side
+pub const NEIGHBORS_4: [IVec2; 4] = [IVec2::NEG_Y, IVec2::X, IVec2::Y, IVec2::NEG_X];
+
+/// Offsets to the pixels sharing a side or a corner
+pub const NEIGHBORS_8: [IVec2; 8] = [
+    IVec2::NEG_Y,
+    IVec2::new(1, -1),
+    IVec2::X,
+    IVec2::ONE,
+    IVec2::Y,
+    IVec2::new(-1, 1),
+    IVec2::NEG_X,
+    IVec2::NEG_ONE,
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Grid<T> {
     width: usize,
@@ -59,7 +74,412 @@ impl<T> Grid<T> {
         UVec2::new(self.width(), self.height())
     }
 
-    pub fn rows(&self) -> std::slice::Chunks<T> {
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
         self.data.chunks(self.width)
     }
+
+    pub fn contains(&self, pos: UVec2) -> bool {
+        pos.cmplt(self.size()).all()
+    }
+
+    pub fn get(&self, pos: UVec2) -> Option<&T> {
+        self.contains(pos).then(|| &self[pos])
+    }
+
+    pub fn get_mut(&mut self, pos: UVec2) -> Option<&mut T> {
+        self.contains(pos).then(|| &mut self[pos])
+    }
+
+    /// `pos + offset`, if it's inside the grid
+    pub fn offset(&self, pos: UVec2, offset: IVec2) -> Option<UVec2> {
+        offset_within(self.size(), pos, offset)
+    }
+
+    /// The positions sharing a side with `pos`, in the order of
+    /// [`NEIGHBORS_4`]
+    pub fn neighbors_4(&self, pos: UVec2) -> impl Iterator<Item = UVec2> + '_ {
+        NEIGHBORS_4
+            .into_iter()
+            .filter_map(move |offset| self.offset(pos, offset))
+    }
+
+    /// The positions sharing a side or a corner with `pos`, in the order of
+    /// [`NEIGHBORS_8`]
+    pub fn neighbors_8(&self, pos: UVec2) -> impl Iterator<Item = UVec2> + '_ {
+        NEIGHBORS_8
+            .into_iter()
+            .filter_map(move |offset| self.offset(pos, offset))
+    }
+
+    /// Every position in reading order, with its value
+    pub fn iter_with_pos(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (UVec2, &T)> + ExactSizeIterator {
+        let width = self.width;
+        self.data.iter().enumerate().map(move |(index, value)| {
+            let pos = UVec2::new((index % width) as u32, (index / width) as u32);
+            (pos, value)
+        })
+    }
+
+    /// The positions whose values match `predicate`, in reading order
+    pub fn positions_where<'a>(
+        &'a self,
+        mut predicate: impl FnMut(&T) -> bool + 'a,
+    ) -> impl Iterator<Item = UVec2> + 'a {
+        self.iter_with_pos()
+            .filter_map(move |(pos, value)| predicate(value).then_some(pos))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            data: self.data.iter().map(f).collect(),
+        }
+    }
+
+    /// Labels the connected areas of positions whose values match
+    /// `predicate`
+    pub fn components(
+        &self,
+        connectivity: Connectivity,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Components {
+        let matches = self.map(&mut predicate);
+        let mut labels = Grid::<Option<usize>>::new(self.size());
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        for start in matches.positions_where(|&matched| matched) {
+            if labels[start].is_some() {
+                continue;
+            }
+            let label = regions.len();
+            let mut region = Region {
+                pixels: 0,
+                min: start,
+                max: start,
+            };
+            labels[start] = Some(label);
+            stack.push(start);
+            while let Some(pos) = stack.pop() {
+                region.pixels += 1;
+                region.min = region.min.min(pos);
+                region.max = region.max.max(pos);
+                let mut visit = |neighbor: UVec2| {
+                    if matches[neighbor] && labels[neighbor].is_none() {
+                        labels[neighbor] = Some(label);
+                        stack.push(neighbor);
+                    }
+                };
+                match connectivity {
+                    Connectivity::Four => self.neighbors_4(pos).for_each(&mut visit),
+                    Connectivity::Eight => self.neighbors_8(pos).for_each(&mut visit),
+                }
+            }
+            regions.push(region);
+        }
+        Components { labels, regions }
+    }
+
+    /// Copies the `size` area starting at `min` into a new grid
+    pub fn sub_grid(&self, min: UVec2, size: UVec2) -> Self
+    where
+        T: Clone,
+    {
+        let columns = min.x as usize..(min.x + size.x) as usize;
+        let data = self
+            .rows()
+            .skip(min.y as usize)
+            .take(size.y as usize)
+            .flat_map(|row| row[columns.clone()].iter().cloned())
+            .collect();
+        Self::from_vec(data, size.x as usize)
+    }
+
+    /// Swaps rows and columns
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.remap(UVec2::new(self.height(), self.width()), |pos| {
+            UVec2::new(pos.y, pos.x)
+        })
+    }
+
+    pub fn flip_horizontal(&self) -> Self
+    where
+        T: Clone,
+    {
+        let max = self.size() - 1;
+        self.remap(self.size(), |pos| UVec2::new(max.x - pos.x, pos.y))
+    }
+
+    pub fn flip_vertical(&self) -> Self
+    where
+        T: Clone,
+    {
+        let max = self.size() - 1;
+        self.remap(self.size(), |pos| UVec2::new(pos.x, max.y - pos.y))
+    }
+
+    /// Rotates 90 degrees clockwise
+    pub fn rotate_90(&self) -> Self
+    where
+        T: Clone,
+    {
+        let max = self.size() - 1;
+        self.remap(UVec2::new(self.height(), self.width()), |pos| {
+            UVec2::new(pos.y, max.y - pos.x)
+        })
+    }
+
+    pub fn rotate_180(&self) -> Self
+    where
+        T: Clone,
+    {
+        let max = self.size() - 1;
+        self.remap(self.size(), |pos| max - pos)
+    }
+
+    /// Rotates 90 degrees counter-clockwise
+    pub fn rotate_270(&self) -> Self
+    where
+        T: Clone,
+    {
+        let max = self.size() - 1;
+        self.remap(UVec2::new(self.height(), self.width()), |pos| {
+            UVec2::new(max.x - pos.y, pos.x)
+        })
+    }
+
+    /// Builds a `size` grid where each position is copied from
+    /// `source(pos)` in `self`
+    fn remap(&self, size: UVec2, source: impl Fn(UVec2) -> UVec2) -> Self
+    where
+        T: Clone,
+    {
+        let data = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .map(|pos| self[source(pos)].clone())
+            .collect();
+        Self::from_vec(data, size.x as usize)
+    }
+}
+
+/// Which positions count as touching when finding [`Components`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Sharing a side
+    Four,
+    /// Sharing a side or a corner
+    Eight,
+}
+
+/// The connected areas of a [`Grid`]
+#[derive(Debug, Clone)]
+pub struct Components {
+    /// The index into [`Self::regions`] of each position, or `None` if
+    /// it's not in any of them
+    pub labels: Grid<Option<usize>>,
+    /// In reading order of their first position
+    pub regions: Vec<Region>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// How many positions are in the region
+    pub pixels: usize,
+    /// The corners of the bounding box, inclusive
+    pub min: UVec2,
+    pub max: UVec2,
+}
+
+impl Region {
+    pub fn size(&self) -> UVec2 {
+        self.max - self.min + 1
+    }
+}
+
+/// `pos + offset`, if it's inside a grid of `size`
+pub fn offset_within(size: UVec2, pos: UVec2, offset: IVec2) -> Option<UVec2> {
+    let pos = pos
+        .x
+        .checked_add_signed(offset.x)
+        .zip(pos.y.checked_add_signed(offset.y))
+        .map(UVec2::from)?;
+    pos.cmplt(size).all().then_some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ```text
+    /// 1 2 3
+    /// 4 5 6
+    /// ```
+    fn grid_3x2() -> Grid<u8> {
+        Grid::from_vec(vec![1, 2, 3, 4, 5, 6], 3)
+    }
+
+    fn values<T: Copy>(grid: &Grid<T>) -> Vec<T> {
+        grid.rows().flatten().copied().collect()
+    }
+
+    #[test]
+    fn out_of_bounds_positions_are_none() {
+        let mut grid = grid_3x2();
+        assert_eq!(grid.get(UVec2::new(2, 1)), Some(&6));
+        assert_eq!(grid.get(UVec2::new(3, 0)), None);
+        assert_eq!(grid.get(UVec2::new(0, 2)), None);
+        assert_eq!(grid.get_mut(UVec2::new(3, 1)), None);
+        assert!(!grid.contains(UVec2::new(u32::MAX, 0)));
+    }
+
+    #[test]
+    fn offsets_stay_inside() {
+        let grid = grid_3x2();
+        assert_eq!(grid.offset(UVec2::ONE, IVec2::NEG_ONE), Some(UVec2::ZERO));
+        assert_eq!(grid.offset(UVec2::ZERO, IVec2::NEG_X), None);
+        assert_eq!(grid.offset(UVec2::ZERO, IVec2::NEG_Y), None);
+        assert_eq!(grid.offset(UVec2::new(2, 1), IVec2::X), None);
+        assert_eq!(grid.offset(UVec2::new(2, 1), IVec2::Y), None);
+        assert_eq!(grid.offset(UVec2::new(2, 1), IVec2::new(i32::MIN, 0)), None);
+        assert_eq!(
+            grid.offset(UVec2::new(2, 1), IVec2::new(-2, -1)),
+            Some(UVec2::ZERO)
+        );
+    }
+
+    #[test]
+    fn neighbors_keep_their_order_at_edges() {
+        let grid = Grid::<u8>::new(UVec2::splat(3));
+        let neighbors_4 = |pos| grid.neighbors_4(pos).collect::<Vec<_>>();
+        let neighbors_8 = |pos| grid.neighbors_8(pos).collect::<Vec<_>>();
+
+        assert_eq!(
+            neighbors_4(UVec2::ONE),
+            [(1, 0), (2, 1), (1, 2), (0, 1)].map(UVec2::from)
+        );
+        assert_eq!(neighbors_4(UVec2::ZERO), [(1, 0), (0, 1)].map(UVec2::from));
+        assert_eq!(
+            neighbors_4(UVec2::new(2, 1)),
+            [(2, 0), (2, 2), (1, 1)].map(UVec2::from)
+        );
+        assert_eq!(
+            neighbors_4(UVec2::splat(2)),
+            [(2, 1), (1, 2)].map(UVec2::from)
+        );
+
+        assert_eq!(
+            neighbors_8(UVec2::ONE),
+            [
+                (1, 0),
+                (2, 0),
+                (2, 1),
+                (2, 2),
+                (1, 2),
+                (0, 2),
+                (0, 1),
+                (0, 0)
+            ]
+            .map(UVec2::from)
+        );
+        assert_eq!(
+            neighbors_8(UVec2::ZERO),
+            [(1, 0), (1, 1), (0, 1)].map(UVec2::from)
+        );
+        assert_eq!(
+            neighbors_8(UVec2::new(1, 2)),
+            [(1, 1), (2, 1), (2, 2), (0, 2), (0, 1)].map(UVec2::from)
+        );
+    }
+
+    #[test]
+    fn positions_are_in_reading_order() {
+        let grid = grid_3x2();
+        let positions: Vec<_> = grid.iter_with_pos().collect();
+        assert_eq!(
+            positions,
+            [
+                (UVec2::new(0, 0), &1),
+                (UVec2::new(1, 0), &2),
+                (UVec2::new(2, 0), &3),
+                (UVec2::new(0, 1), &4),
+                (UVec2::new(1, 1), &5),
+                (UVec2::new(2, 1), &6),
+            ]
+        );
+        assert_eq!(
+            grid.iter_with_pos().next_back(),
+            Some((UVec2::new(2, 1), &6))
+        );
+
+        let odd: Vec<_> = grid.positions_where(|value| value % 2 == 1).collect();
+        assert_eq!(odd, [(0, 0), (2, 0), (1, 1)].map(UVec2::from));
+    }
+
+    #[test]
+    fn map_keeps_the_shape() {
+        let mapped = grid_3x2().map(|&value| value > 3);
+        assert_eq!(mapped.size(), UVec2::new(3, 2));
+        assert_eq!(values(&mapped), [false, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn rotate_90_turns_clockwise() {
+        let rotated = grid_3x2().rotate_90();
+        assert_eq!(rotated.size(), UVec2::new(2, 3));
+        assert_eq!(values(&rotated), [4, 1, 5, 2, 6, 3]);
+    }
+
+    #[test]
+    fn rotate_270_turns_counter_clockwise() {
+        let rotated = grid_3x2().rotate_270();
+        assert_eq!(rotated.size(), UVec2::new(2, 3));
+        assert_eq!(values(&rotated), [3, 6, 2, 5, 1, 4]);
+    }
+
+    #[test]
+    fn rotate_180_reverses() {
+        let rotated = grid_3x2().rotate_180();
+        assert_eq!(rotated.size(), UVec2::new(3, 2));
+        assert_eq!(values(&rotated), [6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn rotating_four_times_is_identity() {
+        let grid = grid_3x2();
+        assert_eq!(grid.rotate_90().rotate_90().rotate_90().rotate_90(), grid);
+        assert_eq!(
+            grid.rotate_270().rotate_270().rotate_270().rotate_270(),
+            grid
+        );
+        assert_eq!(grid.rotate_90().rotate_270(), grid);
+        assert_eq!(grid.rotate_90().rotate_90(), grid.rotate_180());
+    }
+
+    #[test]
+    fn flips_mirror_rows_and_columns() {
+        let grid = grid_3x2();
+        assert_eq!(values(&grid.flip_horizontal()), [3, 2, 1, 6, 5, 4]);
+        assert_eq!(values(&grid.flip_vertical()), [4, 5, 6, 1, 2, 3]);
+    }
+
+    #[test]
+    fn flipping_twice_is_identity() {
+        let grid = grid_3x2();
+        assert_eq!(grid.flip_horizontal().flip_horizontal(), grid);
+        assert_eq!(grid.flip_vertical().flip_vertical(), grid);
+        assert_eq!(grid.flip_horizontal().flip_vertical(), grid.rotate_180());
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let grid = grid_3x2();
+        let transposed = grid.transpose();
+        assert_eq!(transposed.size(), UVec2::new(2, 3));
+        assert_eq!(values(&transposed), [1, 4, 2, 5, 3, 6]);
+        assert_eq!(transposed.transpose(), grid);
+    }
 }
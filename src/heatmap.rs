@@ -0,0 +1,205 @@
+//! Colors each pixel by when the ordering reaches it, to show at a glance
+//! how the ordering sweeps across the art
+
+use std::{array, path::Path};
+
+use bevy::prelude::*;
+use image::RgbaImage;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{Pixel, PixelColor},
+    export::ExportSettings,
+    game::{FrameAdvanced, GameType},
+    mesh_generation::{MulticolorMesh, MulticolorMeshMaterial},
+    ordering::CurrentOrdering,
+    GameState,
+};
+
+/// From the first pixel to the last
+const GRADIENT: [[u8; 3]; 5] = [
+    [68, 1, 84],
+    [59, 82, 139],
+    [33, 145, 140],
+    [94, 201, 98],
+    [253, 231, 37],
+];
+
+/// How opaque the overlay is, so the game still shows through it
+const OVERLAY_ALPHA: u8 = 160;
+
+/// In front of everything the games draw
+const OVERLAY_Z: f32 = 10.0;
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmap>().add_system(
+            frame_advanced_system
+                .run_if(GameState::current_is_play)
+                .run_if_resource_exists::<CurrentOrdering>(),
+        );
+        for game in GameType::ALL {
+            app.add_enter_system(GameState::Play(game), enter_system)
+                .add_exit_system(GameState::Play(game), exit_system);
+        }
+    }
+}
+
+#[derive(Debug, Resource)]
+pub struct Heatmap {
+    /// Draws the heatmap over the games
+    pub overlay: bool,
+    pub path: String,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self {
+            overlay: false,
+            path: "heatmap.png".to_owned(),
+        }
+    }
+}
+
+impl Heatmap {
+    /// Draws each of `pixels` in the color for its index, in an image of
+    /// art that's `size` pixels
+    pub fn render(
+        pixels: &[Pixel],
+        size: UVec2,
+        export: &ExportSettings,
+        clear_color: Color,
+    ) -> RgbaImage {
+        let scale = export.scale.max(1);
+        let mut image = RgbaImage::new(size.x * scale, size.y * scale);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                export.draw_pixel(&mut image, UVec2::new(x, y), None, clear_color);
+            }
+        }
+        for (index, pixel) in pixels.iter().enumerate() {
+            let color = color(index, pixels.len(), u8::MAX);
+            export.draw_pixel(&mut image, pixel.pos, Some(color), clear_color);
+        }
+        image
+    }
+
+    /// Renders and saves to [`Self::path`], returning a message for the user
+    pub fn save(
+        &self,
+        pixels: &[Pixel],
+        size: UVec2,
+        export: &ExportSettings,
+        clear_color: Color,
+    ) -> Result<String, String> {
+        Self::render(pixels, size, export, clear_color)
+            .save(Path::new(&self.path))
+            .map_err(|e| format!("Unable to save image: {e}"))?;
+        Ok(format!("Saved {}", self.path))
+    }
+}
+
+/// The color along [`GRADIENT`] of the pixel at `index` out of `len`
+pub fn color(index: usize, len: usize, alpha: u8) -> PixelColor {
+    let t = index as f32 / (len.max(2) - 1) as f32 * (GRADIENT.len() - 1) as f32;
+    let i = (t as usize).min(GRADIENT.len() - 2);
+    let f = t - i as f32;
+    let [r, g, b] = array::from_fn(|c| {
+        (GRADIENT[i][c] as f32 * (1.0 - f) + GRADIENT[i + 1][c] as f32 * f).round() as u8
+    });
+    PixelColor::from([r, g, b, alpha])
+}
+
+#[derive(Debug, Component)]
+struct HeatmapOverlay;
+
+fn spawn_overlay(
+    commands: &mut Commands,
+    material: &MulticolorMeshMaterial,
+    meshes: &mut Assets<Mesh>,
+    pixels: &[Pixel],
+) {
+    let (mut bundle, mut editor) = MulticolorMesh::bundle(material, meshes);
+    for (index, &pixel) in pixels.iter().enumerate() {
+        editor.add_pixel(Pixel {
+            color: color(index, pixels.len(), OVERLAY_ALPHA),
+            ..pixel
+        });
+    }
+    bundle.transform.translation.z = OVERLAY_Z;
+    commands.spawn((HeatmapOverlay, bundle));
+}
+
+fn enter_system(
+    mut commands: Commands,
+    heatmap: Res<Heatmap>,
+    ordering: Option<Res<CurrentOrdering>>,
+    material: Res<MulticolorMeshMaterial>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if let (true, Some(ordering)) = (heatmap.overlay, ordering) {
+        spawn_overlay(&mut commands, &material, &mut meshes, ordering.pixels());
+    }
+}
+
+/// Animated art plays a new ordering for each frame
+fn frame_advanced_system(
+    mut commands: Commands,
+    mut frame_events: EventReader<FrameAdvanced>,
+    heatmap: Res<Heatmap>,
+    ordering: Res<CurrentOrdering>,
+    material: Res<MulticolorMeshMaterial>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    overlay_query: Query<Entity, With<HeatmapOverlay>>,
+) {
+    if frame_events.iter().count() == 0 || !heatmap.overlay {
+        return;
+    }
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    spawn_overlay(&mut commands, &material, &mut meshes, ordering.pixels());
+}
+
+fn exit_system(mut commands: Commands, overlay_query: Query<Entity, With<HeatmapOverlay>>) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(color: PixelColor) -> [u8; 3] {
+        let [r, g, b, _] = color.rgba();
+        [r, g, b]
+    }
+
+    #[test]
+    fn ends_match_gradient() {
+        for len in [2, 3, 10, 1000] {
+            assert_eq!(rgb(color(0, len, 255)), GRADIENT[0]);
+            assert_eq!(rgb(color(len - 1, len, 255)), GRADIENT[GRADIENT.len() - 1]);
+        }
+        assert_eq!(rgb(color(2, GRADIENT.len(), 255)), GRADIENT[2]);
+        assert_eq!(color(0, 10, OVERLAY_ALPHA).rgba()[3], OVERLAY_ALPHA);
+    }
+
+    #[test]
+    fn single_pixel_uses_first_color() {
+        assert_eq!(rgb(color(0, 1, 255)), GRADIENT[0]);
+
+        let pixels = [Pixel::new(1, 0, PixelColor::from([1, 2, 3, 255]))];
+        let export = ExportSettings {
+            scale: 1,
+            ..default()
+        };
+        let image = Heatmap::render(&pixels, UVec2::splat(2), &export, Color::NONE);
+        let [r, g, b] = GRADIENT[0];
+        assert_eq!(image.get_pixel(1, 0).0, [r, g, b, 255]);
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+    }
+}
@@ -15,6 +15,16 @@ pub const LEFT_KEYS: [KeyCode; 2] = [KeyCode::A, KeyCode::Left];
 
 pub const EXIT_KEYS: [KeyCode; 1] = [KeyCode::Escape];
 
+pub const EXPORT_KEYS: [KeyCode; 1] = [KeyCode::P];
+
+pub const HINT_KEYS: [KeyCode; 1] = [KeyCode::H];
+
+pub const ZOOM_IN_KEYS: [KeyCode; 2] = [KeyCode::Equals, KeyCode::NumpadAdd];
+pub const ZOOM_OUT_KEYS: [KeyCode; 2] = [KeyCode::Minus, KeyCode::NumpadSubtract];
+pub const FIT_KEYS: [KeyCode; 2] = [KeyCode::Key0, KeyCode::Numpad0];
+
+pub const PAN_BUTTONS: [MouseButton; 2] = [MouseButton::Left, MouseButton::Middle];
+
 pub const WAIT_REPEAT_DURATION: Duration = Duration::from_millis(500);
 
 pub const REPEAT_STEP_DURATION: Duration = Duration::from_millis(1000 / 30);
@@ -1,54 +1,134 @@
 #![warn(clippy::todo)]
 
-use std::{env, path::Path};
+use std::process;
 
 use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 
-use art::{Art, ArtName};
+use art::{ArtName, ArtPath};
+use cli::{Cli, Command};
+use gallery::Gallery;
 use game::GameType;
-use ordering::OrderingType;
+use ordering::{CurrentOrdering, OrderingType, Orderings};
+use palette::ColorReduction;
+use playlist::Playlist;
 
 mod art;
+mod aseprite;
+mod bit_grid;
 mod camera;
+mod check;
+mod cli;
+mod editor;
+mod export;
+mod gallery;
 mod game;
 mod graph;
 mod grid;
+mod heatmap;
 mod input;
 mod menu;
 mod mesh_generation;
 mod ordering;
+mod palette;
+mod playlist;
 mod prefabs;
+mod reload;
 mod side;
+mod status;
+mod svg;
+mod text_art;
+mod timelapse;
 
 fn main() {
-    let mut enter_state = GameState::AwaitingImage;
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Check { json }) => {
+            process::exit(if check::run(cli.art, json) { 0 } else { 1 });
+        }
+        Some(Command::Timelapse {
+            timelapse,
+            export,
+            clear_color,
+        }) => {
+            let ok = timelapse::run(cli.art, cli.selection, &timelapse, &export, clear_color);
+            process::exit(if ok { 0 } else { 1 });
+        }
+        None => {}
+    }
+
+    let gallery = Gallery::load();
+    let mut enter_state = if gallery.is_empty() {
+        GameState::AwaitingImage
+    } else {
+        GameState::Gallery
+    };
     let mut app = App::new();
-    if let Some(path) = env::args().nth(1) {
-        let path = Path::new(&path);
-        match Art::load_from_path(path) {
-            Ok(art) => {
-                app.insert_resource(art);
-                app.insert_resource(ArtName(path.file_name().map_or_else(
-                    || "{unknown}".to_owned(),
-                    |name| name.to_string_lossy().to_string(),
-                )));
-                enter_state = GameState::MainMenu;
-            }
-            Err(err) => {
-                app.insert_resource(ArtName(err));
-            }
+    let seed = cli.seed.unwrap_or_else(|| fastrand::u64(..));
+    fastrand::seed(seed);
+    let color_reduction = match cli.colors {
+        Some(count) => ColorReduction {
+            count,
+            on_load: true,
+        },
+        None => ColorReduction::default(),
+    };
+    let (mut entries, errors) = playlist::load_args(cli.art);
+    for entry in &mut entries {
+        color_reduction.apply_on_load(&mut entry.frames);
+    }
+    for err in &errors {
+        eprintln!("{err}");
+    }
+    if let Some(mut playlist) = Playlist::new(entries) {
+        playlist.looping = cli.looping;
+        if cli.shuffle {
+            playlist.shuffle_all(seed);
         }
+        let entry = playlist.current();
+        let art = entry.frames.current();
+        enter_state = cli.start.unwrap_or(GameState::MainMenu);
+        if let GameState::Play(_) = enter_state {
+            let mut orderings = Orderings::default();
+            app.insert_resource(CurrentOrdering::init(
+                &mut orderings,
+                cli.selection.ordering,
+                cli.selection.options,
+                &art,
+            ));
+            app.insert_resource(orderings);
+        }
+        app.insert_resource(art);
+        app.insert_resource(entry.frames.clone());
+        app.insert_resource(ArtName(entry.name.clone()));
+        if let Some(path) = &entry.path {
+            app.insert_resource(ArtPath::new(path.clone()));
+        }
+        if playlist.len() > 1 {
+            app.insert_resource(playlist);
+        }
+    } else if let Some(err) = errors.last() {
+        app.insert_resource(ArtName(err.clone()));
     }
     app.add_loopless_state(enter_state)
+        .insert_resource(cli.selection)
+        .insert_resource(gallery)
+        .insert_resource(Seed(seed))
+        .insert_resource(color_reduction)
         .insert_resource(ClearColor(Color::GRAY))
         .add_plugins(DefaultPlugins)
         .add_plugin(camera::CameraPlugin)
+        .add_plugin(editor::EditorPlugin)
+        .add_plugin(export::ExportPlugin)
+        .add_plugin(gallery::GalleryPlugin)
         .add_plugin(game::GamePlugin)
+        .add_plugin(heatmap::HeatmapPlugin)
         .add_plugin(menu::MenuPlugin)
         .add_plugin(mesh_generation::MeshGenerationPlugin)
         .add_plugin(ordering::OrderingPlugin)
         .add_plugin(prefabs::PrefabsPlugin)
+        .add_plugin(reload::ReloadPlugin)
+        .add_plugin(status::StatusPlugin)
         .run();
 }
 
@@ -57,20 +137,33 @@ enum GameState {
     #[default]
     AwaitingImage,
     MainMenu,
+    Editor,
+    Gallery,
     Generate(OrderingType),
     Play(GameType),
 }
 
 impl GameState {
     pub fn is_menu(self) -> bool {
-        matches!(self, Self::AwaitingImage | Self::MainMenu)
+        matches!(
+            self,
+            Self::AwaitingImage | Self::MainMenu | Self::Editor | Self::Gallery
+        )
     }
 
     pub fn current_is_menu(state: Res<CurrentState<Self>>) -> bool {
         state.0.is_menu()
     }
+
+    pub fn current_is_play(state: Res<CurrentState<Self>>) -> bool {
+        matches!(state.0, Self::Play(_))
+    }
 }
 
+/// Seeds anything random, so runs can be repeated with `--seed`
+#[derive(Debug, Clone, Copy, Resource, Deref)]
+struct Seed(u64);
+
 fn world_pos(pos: UVec2) -> Vec2 {
     pos.as_vec2() * Vec2::new(1.0, -1.0)
 }
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use bevy::prelude::*;
 use bevy_egui::{
     egui::{self, RichText, TextStyle},
@@ -6,13 +8,21 @@ use bevy_egui::{
 use iyes_loopless::prelude::*;
 
 use crate::{
-    art::{Art, ArtName},
+    art::{Art, ArtFrames, ArtName, ArtPath},
     camera::AreaTrackingProjection,
+    editor::Editor,
+    gallery::Gallery,
     game::GameType,
-    ordering::{CurrentOrdering, OrderingType, Orderings},
+    ordering::{CurrentOrdering, OrderingOptions, OrderingType, Orderings},
+    palette::{ColorReduction, Palette},
+    playlist::Playlist,
+    status::Status,
     GameState,
 };
 
+mod art_options;
+mod export_options;
+
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
@@ -21,24 +31,43 @@ impl Plugin for MenuPlugin {
         struct Label;
 
         app.add_plugin(EguiPlugin)
+            .init_resource::<Selection>()
             .add_system(
                 show_menu_system
                     .run_in_state(GameState::MainMenu)
                     .after(Label),
             )
+            .add_system(
+                art_options::art_options_system
+                    .run_in_state(GameState::MainMenu)
+                    .after(Label),
+            )
+            .add_system(
+                export_options::export_options_system
+                    .run_in_state(GameState::MainMenu)
+                    .after(Label),
+            )
+            .add_system(
+                export_options::timelapse_task_system
+                    .run_if_resource_exists::<export_options::TimelapseTask>(),
+            )
             .add_system(awaiting_image_system.label(Label));
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// What is picked in the main menu
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Selection {
+    pub ordering: OrderingType,
+    pub options: OrderingOptions,
+    pub game: GameType,
+}
+
 pub fn show_menu_system(
     mut commands: Commands,
     mut egui_context: ResMut<EguiContext>,
     windows: Res<Windows>,
-    mut ordering: Local<OrderingType>,
-    mut is_reversed: Local<bool>,
-    mut is_by_color: Local<bool>,
-    mut game: Local<GameType>,
+    mut selection: ResMut<Selection>,
     mut orderings: ResMut<Orderings>,
     art: Res<Art>,
     mut projection_query: Query<&mut AreaTrackingProjection>,
@@ -50,7 +79,7 @@ pub fn show_menu_system(
         style.text_styles.get_mut(&TextStyle::Button).unwrap().size = 30.0;
     };
 
-    let mut set_ordering = *ordering;
+    let mut set_ordering = selection.ordering;
     egui::SidePanel::left("ordering")
         .min_width(window_width / 2.0 - 15.0)
         .max_width(window_width / 2.0)
@@ -63,7 +92,7 @@ pub fn show_menu_system(
 
                 let mut show_option = |new_ordering, label: &str| {
                     if ui
-                        .selectable_label(*ordering == new_ordering, label)
+                        .selectable_label(selection.ordering == new_ordering, label)
                         .clicked()
                     {
                         set_ordering = new_ordering;
@@ -76,25 +105,36 @@ pub fn show_menu_system(
                 ui.add_space(30.0);
 
                 if ui
-                    .button(if *is_reversed {
+                    .button(if selection.options.reversed {
                         "Reversed"
                     } else {
                         "Not Reversed"
                     })
                     .clicked()
                 {
-                    *is_reversed = !*is_reversed
+                    selection.options.reversed = !selection.options.reversed
                 }
 
                 if ui
-                    .button(if *is_by_color {
+                    .button(if selection.options.by_color {
                         "By Color"
                     } else {
                         "One Shape"
                     })
                     .clicked()
                 {
-                    *is_by_color = !*is_by_color
+                    selection.options.by_color = !selection.options.by_color
+                }
+
+                if ui
+                    .button(if selection.options.by_island {
+                        "By Island"
+                    } else {
+                        "Whole Art"
+                    })
+                    .clicked()
+                {
+                    selection.options.by_island = !selection.options.by_island
                 }
             });
 
@@ -104,20 +144,16 @@ pub fn show_menu_system(
                 .show_inside(ui, |ui| {
                     ui.vertical_centered(|ui| {
                         if ui.button(RichText::new("Generate!").size(40.0)).clicked() {
-                            let size = art.size().as_vec2();
-                            projection_query.single_mut().tracked_area = Rect {
-                                min: Vec2::new(-1.0, -size.y),
-                                max: Vec2::new(size.x, 1.0),
-                            };
-
-                            commands.insert_resource(NextState(GameState::Generate(*ordering)))
+                            projection_query.single_mut().track_art(&art);
+                            commands
+                                .insert_resource(NextState(GameState::Generate(selection.ordering)))
                         }
                     });
                 });
         });
-    *ordering = set_ordering;
+    selection.ordering = set_ordering;
 
-    let mut set_game = *game;
+    let mut set_game = selection.game;
     egui::SidePanel::right("game")
         .min_width(window_width / 2.0)
         .max_width(window_width / 2.0)
@@ -130,7 +166,10 @@ pub fn show_menu_system(
                 ui.heading("Game");
 
                 let mut show_option = |new_game, label: &str| {
-                    if ui.selectable_label(*game == new_game, label).clicked() {
+                    if ui
+                        .selectable_label(selection.game == new_game, label)
+                        .clicked()
+                    {
                         set_game = new_game;
                     };
                 };
@@ -146,24 +185,20 @@ pub fn show_menu_system(
                         if ui.button(RichText::new("Play!").size(40.0)).clicked() {
                             commands.insert_resource(CurrentOrdering::init(
                                 &mut orderings,
-                                *ordering,
+                                selection.ordering,
+                                selection.options,
                                 &art,
                             ));
-
-                            let size = art.size().as_vec2();
-                            projection_query.single_mut().tracked_area = Rect {
-                                min: Vec2::new(-1.0, -size.y),
-                                max: Vec2::new(size.x, 1.0),
-                            };
-
-                            commands.insert_resource(NextState(GameState::Play(*game)));
+                            projection_query.single_mut().track_art(&art);
+                            commands.insert_resource(NextState(GameState::Play(selection.game)));
                         }
                     });
                 });
         });
-    *game = set_game;
+    selection.game = set_game;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn awaiting_image_system(
     mut commands: Commands,
     state: Res<CurrentState<GameState>>,
@@ -171,6 +206,8 @@ fn awaiting_image_system(
     art_name: Option<Res<ArtName>>,
     mut file_events: EventReader<FileDragAndDrop>,
     mut orderings: ResMut<Orderings>,
+    mut gallery: ResMut<Gallery>,
+    color_reduction: Res<ColorReduction>,
 ) {
     if state.0.is_menu() {
         if let Some(art_name) = art_name {
@@ -179,8 +216,22 @@ fn awaiting_image_system(
 
         if state.0 == GameState::AwaitingImage {
             egui::CentralPanel::default().show(egui_context.ctx_mut(), |ui| {
-                ui.centered_and_justified(|ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(ui.available_height() / 3.0);
                     ui.heading(RichText::new("Drag image here to begin").size(100.0));
+                    if ui
+                        .button(RichText::new("Or draw some").size(40.0))
+                        .clicked()
+                    {
+                        commands.insert_resource(Editor::new(UVec2::splat(16)));
+                        commands.insert_resource(NextState(GameState::Editor));
+                    }
+                    if ui
+                        .button(RichText::new("Or pick from the gallery").size(40.0))
+                        .clicked()
+                    {
+                        commands.insert_resource(NextState(GameState::Gallery));
+                    }
                 });
             });
         }
@@ -188,20 +239,51 @@ fn awaiting_image_system(
 
     for file_event in file_events.iter() {
         if let FileDragAndDrop::DroppedFile { path_buf, .. } = file_event {
-            match Art::load_from_path(path_buf) {
-                Ok(art) => {
-                    commands.insert_resource(art);
-                    commands.insert_resource(ArtName(path_buf.file_name().map_or_else(
-                        || "{unknown}".to_owned(),
-                        |name| name.to_string_lossy().to_string(),
-                    )));
-                    commands.insert_resource(NextState(GameState::MainMenu));
-                    orderings.clear();
-                }
-                Err(err) => {
-                    commands.insert_resource(ArtName(err));
+            if let Some("gpl" | "hex") = path_buf.extension().and_then(|e| e.to_str()) {
+                match Palette::load_from_path(path_buf) {
+                    Ok(palette) => {
+                        let message = format!("Loaded palette {}", palette.name);
+                        commands.insert_resource(Status::new(message));
+                        commands.insert_resource(palette);
+                    }
+                    Err(err) => commands.insert_resource(Status::new(err)),
                 }
+                continue;
             }
+
+            open_art(
+                &mut commands,
+                path_buf,
+                &mut orderings,
+                &mut gallery,
+                &color_reduction,
+            );
+        }
+    }
+}
+
+/// Loads the art at `path`, the same way as dropping it on the window
+pub fn open_art(
+    commands: &mut Commands,
+    path: &Path,
+    orderings: &mut Orderings,
+    gallery: &mut Gallery,
+    color_reduction: &ColorReduction,
+) {
+    match ArtFrames::load_from_path(path) {
+        Ok(mut frames) => {
+            color_reduction.apply_on_load(&mut frames);
+            commands.insert_resource(frames.current());
+            commands.insert_resource(frames);
+            commands.insert_resource(ArtName::from_path(path));
+            commands.insert_resource(ArtPath::new(path.to_owned()));
+            commands.insert_resource(NextState(GameState::MainMenu));
+            commands.remove_resource::<Playlist>();
+            orderings.clear();
+            gallery.add_recent(path);
+        }
+        Err(err) => {
+            commands.insert_resource(ArtName(err));
         }
     }
 }
@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{Art, ArtFrames, ArtName, ArtPath, ArtTransform, PixelColor},
+    editor::Editor,
+    ordering::Orderings,
+    palette::{ColorReduction, Palette},
+    playlist::Playlist,
+    status::Status,
+    GameState, Seed,
+};
+
+#[derive(Debug, Deref, DerefMut)]
+pub struct TileSize(UVec2);
+
+impl Default for TileSize {
+    fn default() -> Self {
+        Self(UVec2::splat(16))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn art_options_system(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut orderings: ResMut<Orderings>,
+    art: Res<Art>,
+    mut frames: ResMut<ArtFrames>,
+    art_name: Res<ArtName>,
+    mut playlist: Option<ResMut<Playlist>>,
+    palette: Option<Res<Palette>>,
+    mut tile_size: Local<TileSize>,
+    mut color_reduction: ResMut<ColorReduction>,
+    seed: Res<Seed>,
+) {
+    let mut art_changed = false;
+    egui::Window::new("Art")
+        .default_width(200.0)
+        .show(egui_context.ctx_mut(), |ui| {
+            if frames.frame_count() > 1 {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!(
+                            "Frame {}/{}",
+                            frames.current + 1,
+                            frames.frame_count()
+                        ))
+                        .clicked()
+                    {
+                        frames.current = (frames.current + 1) % frames.frame_count();
+                        art_changed = true;
+                    }
+
+                    if ui
+                        .button(if frames.animate {
+                            "Animate"
+                        } else {
+                            "Single Frame"
+                        })
+                        .clicked()
+                    {
+                        frames.animate = !frames.animate
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Edit").clicked() {
+                    commands.insert_resource(Editor::from_art(art_name.0.clone(), &art));
+                    commands.insert_resource(NextState(GameState::Editor));
+                }
+                if ui.button("Gallery").clicked() {
+                    commands.insert_resource(NextState(GameState::Gallery));
+                }
+            });
+
+            ui.collapsing("Transform", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for transform in ArtTransform::ALL {
+                        if ui.button(transform.label()).clicked() {
+                            frames.transform(transform);
+                            art_changed = true;
+                        }
+                    }
+                });
+            });
+
+            let mut toggled_layer = None;
+            if let Some(aseprite) = &mut frames.aseprite {
+                ui.collapsing("Layers", |ui| {
+                    for (index, layer) in aseprite.layers.iter_mut().enumerate() {
+                        if !layer.is_group()
+                            && ui.checkbox(&mut layer.included, &layer.name).changed()
+                        {
+                            toggled_layer = Some(index);
+                        }
+                    }
+                });
+            }
+            if let Some(index) = toggled_layer {
+                match frames.recompose() {
+                    Ok(()) => art_changed = true,
+                    Err(err) => {
+                        let layer = &mut frames.aseprite.as_mut().unwrap().layers[index];
+                        layer.included = !layer.included;
+                        commands.insert_resource(Status::new(err));
+                    }
+                }
+            }
+
+            if let Some(playlist) = &mut playlist {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!(
+                            "Playlist {}/{}",
+                            playlist.position() + 1,
+                            playlist.len()
+                        ))
+                        .clicked()
+                    {
+                        let entry = playlist.advance();
+                        *frames = entry.frames.clone();
+                        commands.insert_resource(ArtName(entry.name.clone()));
+                        ArtPath::replace(&mut commands, entry.path.as_deref());
+                        art_changed = true;
+                    }
+
+                    let mut shuffle = playlist.is_shuffled();
+                    if ui.checkbox(&mut shuffle, "Shuffle").changed() {
+                        playlist.set_shuffle(shuffle.then_some(**seed));
+                    }
+                    ui.checkbox(&mut playlist.looping, "Loop");
+                });
+            } else {
+                ui.collapsing("Sprite Sheet", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Tile Size");
+                        ui.add(egui::DragValue::new(&mut tile_size.x).clamp_range(2..=1024));
+                        ui.label("x");
+                        ui.add(egui::DragValue::new(&mut tile_size.y).clamp_range(2..=1024));
+                    });
+
+                    let tiles = if ui.button("Slice").clicked() {
+                        Some((art.slice(**tile_size), Vec::new()))
+                    } else if ui.button("Auto-Detect Tiles").clicked() {
+                        Some(art.slice_at_gutters())
+                    } else {
+                        None
+                    };
+                    if let Some((tiles, errors)) = tiles {
+                        for err in &errors {
+                            eprintln!("{err}");
+                        }
+                        if !errors.is_empty() {
+                            let message =
+                                format!("Skipped {} tiles smaller than 2x2", errors.len());
+                            commands.insert_resource(Status::new(message));
+                        }
+                        if let Some(playlist) = Playlist::from_tiles(&art_name.0, tiles) {
+                            let entry = playlist.current();
+                            *frames = entry.frames.clone();
+                            commands.insert_resource(ArtName(entry.name.clone()));
+                            commands.remove_resource::<ArtPath>();
+                            commands.insert_resource(playlist);
+                            art_changed = true;
+                        } else {
+                            commands.insert_resource(Status::new("Sprite sheet has no tiles"));
+                        }
+                    }
+                });
+            }
+
+            ui.collapsing("Colors", |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut color_reduction.count).clamp_range(2..=256));
+                    if ui.button("Reduce Colors").clicked() {
+                        frames.reduce_colors(color_reduction.count);
+                        art_changed = true;
+                    }
+                });
+                ui.checkbox(&mut color_reduction.on_load, "Reduce Colors on Load");
+
+                if let Some(palette) = &palette {
+                    ui.separator();
+                    ui.label(format!("Palette: {}", palette.name));
+                    ui.horizontal_wrapped(|ui| {
+                        for &color in &palette.colors {
+                            swatch(ui, color);
+                        }
+                    });
+                    if ui.button("Nearest Colors").clicked() {
+                        frames.recolor = Some(palette.nearest_recolor(&frames.source_colors()));
+                        art_changed = true;
+                    }
+                }
+
+                if let Some(recolor) = &mut frames.recolor {
+                    ui.separator();
+                    ui.horizontal_wrapped(|ui| {
+                        for &color in recolor.palette() {
+                            swatch(ui, color);
+                        }
+                    });
+
+                    let max_index = recolor.palette().len() - 1;
+                    let row_height = ui.spacing().interact_size.y;
+                    egui::ScrollArea::vertical().max_height(200.0).show_rows(
+                        ui,
+                        row_height,
+                        recolor.mapping.len(),
+                        |ui, rows| {
+                            for i in rows {
+                                let (from, mut index) = recolor.mapping[i];
+                                ui.horizontal(|ui| {
+                                    swatch(ui, from);
+                                    ui.label("→");
+                                    if ui
+                                        .add(
+                                            egui::DragValue::new(&mut index)
+                                                .clamp_range(0..=max_index),
+                                        )
+                                        .changed()
+                                    {
+                                        art_changed = true;
+                                    }
+                                    swatch(ui, recolor.palette()[index]);
+                                });
+                                recolor.mapping[i].1 = index;
+                            }
+                        },
+                    );
+                }
+                if frames.recolor.is_some() && ui.button("Original Colors").clicked() {
+                    frames.recolor = None;
+                    art_changed = true;
+                }
+            });
+        });
+
+    if art_changed {
+        commands.insert_resource(frames.current());
+        orderings.clear();
+    }
+}
+
+pub fn swatch(ui: &mut egui::Ui, color: PixelColor) {
+    let [r, g, b, a] = color.rgba();
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 2.0, egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+}
@@ -0,0 +1,161 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    art::Art,
+    export::ExportSettings,
+    heatmap::Heatmap,
+    ordering::{CurrentOrdering, Orderings},
+    status::Status,
+    svg::SvgSettings,
+    timelapse::Timelapse,
+};
+
+use super::Selection;
+
+/// A timelapse being saved in the background, since drawing every frame
+/// of big art takes a while
+#[derive(Debug, Resource)]
+pub struct TimelapseTask(Task<Result<String, String>>);
+
+impl TimelapseTask {
+    fn spawn(
+        timelapse: Timelapse,
+        art: Art,
+        ordering: CurrentOrdering,
+        export: ExportSettings,
+        clear_color: Color,
+    ) -> Self {
+        Self(
+            AsyncComputeTaskPool::get()
+                .spawn(async move { timelapse.save(&art, ordering, &export, clear_color) }),
+        )
+    }
+}
+
+pub fn timelapse_task_system(mut commands: Commands, mut task: ResMut<TimelapseTask>) {
+    if let Some(result) =
+        futures_lite::future::block_on(futures_lite::future::poll_once(&mut task.0))
+    {
+        commands.remove_resource::<TimelapseTask>();
+        commands.insert_resource(Status::new(result.unwrap_or_else(|err| err)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn export_options_system(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut orderings: ResMut<Orderings>,
+    art: Res<Art>,
+    selection: Res<Selection>,
+    clear_color: Res<ClearColor>,
+    mut export_settings: ResMut<ExportSettings>,
+    mut timelapse: Local<Timelapse>,
+    timelapse_task: Option<Res<TimelapseTask>>,
+    mut heatmap: ResMut<Heatmap>,
+    mut svg_settings: Local<SvgSettings>,
+) {
+    let ordering = |orderings: &mut Orderings| {
+        CurrentOrdering::init(orderings, selection.ordering, selection.options, &art)
+    };
+    egui::Window::new("Export")
+        .default_width(200.0)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Scale");
+                ui.add(egui::DragValue::new(&mut export_settings.scale).clamp_range(1..=64));
+                ui.checkbox(&mut export_settings.grid, "Grid");
+                ui.checkbox(&mut export_settings.background, "Background");
+            });
+
+            ui.separator();
+            ui.text_edit_singleline(&mut export_settings.path);
+            if ui.button("Export PNG").clicked() {
+                let message = export_settings
+                    .save(&art, None, clear_color.0)
+                    .unwrap_or_else(|err| err);
+                commands.insert_resource(Status::new(message));
+            }
+
+            ui.collapsing("Timelapse", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Pixels per Frame");
+                    ui.add(
+                        egui::DragValue::new(&mut timelapse.pixels_per_frame).clamp_range(1..=4096),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Delay");
+                    ui.add(
+                        egui::DragValue::new(&mut timelapse.delay)
+                            .clamp_range(10..=10000)
+                            .suffix(" ms"),
+                    );
+                    ui.label("Hold");
+                    ui.add(
+                        egui::DragValue::new(&mut timelapse.hold)
+                            .clamp_range(0..=60000)
+                            .suffix(" ms"),
+                    );
+                });
+                ui.text_edit_singleline(&mut timelapse.path);
+                ui.horizontal(|ui| {
+                    let saving = timelapse_task.is_some();
+                    if ui
+                        .add_enabled(!saving, egui::Button::new("Export Timelapse"))
+                        .clicked()
+                    {
+                        commands.insert_resource(TimelapseTask::spawn(
+                            timelapse.clone(),
+                            art.clone(),
+                            ordering(&mut orderings),
+                            export_settings.clone(),
+                            clear_color.0,
+                        ));
+                    }
+                    if saving {
+                        ui.spinner();
+                    }
+                });
+            });
+
+            ui.collapsing("SVG", |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut svg_settings.animate, "Animate");
+                    ui.add_enabled(
+                        svg_settings.animate,
+                        egui::DragValue::new(&mut svg_settings.pixel_delay)
+                            .clamp_range(1..=1000)
+                            .suffix(" ms per pixel"),
+                    );
+                });
+                ui.text_edit_singleline(&mut svg_settings.path);
+                if ui.button("Export SVG").clicked() {
+                    let message = svg_settings
+                        .save(&art, Some(&ordering(&mut orderings)), export_settings.scale)
+                        .unwrap_or_else(|err| err);
+                    commands.insert_resource(Status::new(message));
+                }
+            });
+
+            ui.collapsing("Heatmap", |ui| {
+                ui.checkbox(&mut heatmap.overlay, "Show While Playing");
+                ui.text_edit_singleline(&mut heatmap.path);
+                if ui.button("Export Heatmap").clicked() {
+                    let message = heatmap
+                        .save(
+                            ordering(&mut orderings).pixels(),
+                            art.size(),
+                            &export_settings,
+                            clear_color.0,
+                        )
+                        .unwrap_or_else(|err| err);
+                    commands.insert_resource(Status::new(message));
+                }
+            });
+        });
+}
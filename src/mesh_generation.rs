@@ -1,8 +1,6 @@
-use std::iter;
-
 use bevy::{
     ecs::system::EntityCommands, prelude::*, render::render_resource::PrimitiveTopology,
-    sprite::Mesh2dHandle,
+    sprite::Mesh2dHandle, utils::HashSet,
 };
 
 use crate::{
@@ -30,6 +28,8 @@ pub struct MulticolorMeshMaterial(Handle<ColorMaterial>);
 #[derive(Debug, Component)]
 pub struct MulticolorMesh {
     mesh: Mesh2dHandle,
+    /// The pixel each quad of the mesh draws, if it was added as one
+    quad_pixels: Vec<Option<UVec2>>,
 }
 
 impl MulticolorMesh {
@@ -38,6 +38,25 @@ impl MulticolorMesh {
         material: &MulticolorMeshMaterial,
         meshes: &'m mut Assets<Mesh>,
     ) -> (EntityCommands<'w, 's, 'a>, MulticolorMeshEditor<'m>) {
+        let (bundle, editor) = Self::bundle(material, meshes);
+        (
+            commands.spawn((
+                Self {
+                    mesh: bundle.mesh.clone(),
+                    quad_pixels: Vec::new(),
+                },
+                bundle,
+            )),
+            editor,
+        )
+    }
+
+    /// An empty mesh that isn't a [`MulticolorMesh`], for things drawn over
+    /// the games without being found by their queries
+    pub fn bundle<'m>(
+        material: &MulticolorMeshMaterial,
+        meshes: &'m mut Assets<Mesh>,
+    ) -> (ColorMesh2dBundle, MulticolorMeshEditor<'m>) {
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
         mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new());
@@ -45,22 +64,25 @@ impl MulticolorMesh {
 
         let editor = MulticolorMeshEditor::new(meshes.get_mut(&mesh_handle.0).unwrap());
         (
-            commands.spawn((
-                Self {
-                    mesh: mesh_handle.clone(),
-                },
-                ColorMesh2dBundle {
-                    mesh: mesh_handle,
-                    material: material.0.clone(),
-                    ..default()
-                },
-            )),
+            ColorMesh2dBundle {
+                mesh: mesh_handle,
+                material: material.0.clone(),
+                ..default()
+            },
             editor,
         )
     }
 
-    pub fn edit<'a>(&self, meshes: &'a mut Assets<Mesh>) -> MulticolorMeshEditor<'a> {
-        MulticolorMeshEditor::new(meshes.get_mut(&self.mesh.0).unwrap())
+    pub fn edit<'a>(&'a mut self, meshes: &'a mut Assets<Mesh>) -> MulticolorMeshEditor<'a> {
+        let mut editor = MulticolorMeshEditor::new(meshes.get_mut(&self.mesh.0).unwrap());
+        // Quads added by the editor from `generate` come before any that are
+        // tracked, and aren't pixels
+        let quads = editor.positions.len() / 6;
+        if self.quad_pixels.len() < quads {
+            self.quad_pixels.resize(quads, None);
+        }
+        editor.quad_pixels = Some(&mut self.quad_pixels);
+        editor
     }
 }
 
@@ -68,6 +90,8 @@ impl MulticolorMesh {
 pub struct MulticolorMeshEditor<'a> {
     positions: &'a mut Vec<[f32; 3]>,
     colors: &'a mut Vec<[f32; 4]>,
+    /// Only meshes with a [`MulticolorMesh`] keep track of their pixels
+    quad_pixels: Option<&'a mut Vec<Option<UVec2>>>,
 }
 
 impl<'a> MulticolorMeshEditor<'a> {
@@ -81,7 +105,11 @@ impl<'a> MulticolorMeshEditor<'a> {
             .unwrap();
         use bevy::render::mesh::VertexAttributeValues as Vav;
         if let (Vav::Float32x3(positions), Vav::Float32x4(colors)) = (positions.1, colors.1) {
-            Self { positions, colors }
+            Self {
+                positions,
+                colors,
+                quad_pixels: None,
+            }
         } else {
             panic!()
         }
@@ -94,7 +122,11 @@ impl<'a> MulticolorMeshEditor<'a> {
     }
 
     pub fn add_pixel(&mut self, pixel: Pixel) -> &mut Self {
-        self.add_square(pixel.world_pos(), 1.0, pixel.color)
+        self.add_square(pixel.world_pos(), 1.0, pixel.color);
+        if let Some(quad_pixels) = &mut self.quad_pixels {
+            *quad_pixels.last_mut().unwrap() = Some(pixel.pos);
+        }
+        self
     }
 
     pub fn add_small_pixel(&mut self, pixel: Pixel) -> &mut Self {
@@ -118,6 +150,32 @@ impl<'a> MulticolorMeshEditor<'a> {
         )
     }
 
+    /// Removes the quads added by [`Self::add_pixel`] for any of the pixels
+    /// at `positions`
+    pub fn remove_pixels(&mut self, positions: &HashSet<UVec2>) -> &mut Self {
+        let quad_pixels = self
+            .quad_pixels
+            .as_mut()
+            .expect("only meshes with a MulticolorMesh track their pixels");
+        let keep: Vec<_> = quad_pixels
+            .iter()
+            .map(|pixel| !matches!(pixel, Some(pos) if positions.contains(pos)))
+            .collect();
+        let mut quads = keep.iter();
+        quad_pixels.retain(|_| *quads.next().unwrap());
+        let mut index = 0;
+        self.positions.retain(|_| {
+            index += 1;
+            keep[(index - 1) / 6]
+        });
+        let mut index = 0;
+        self.colors.retain(|_| {
+            index += 1;
+            keep[(index - 1) / 6]
+        });
+        self
+    }
+
     pub fn add_quad(&mut self, positions: [Vec2; 4], color: PixelColor) -> &mut Self {
         let positions = positions.map(|pos| [pos.x, pos.y, 0.0]);
         self.positions.extend([
@@ -128,8 +186,49 @@ impl<'a> MulticolorMeshEditor<'a> {
             positions[3],
             positions[0],
         ]);
-        self.colors
-            .extend(iter::repeat(<[f32; 4]>::from(color)).take(6));
+        self.colors.extend([<[f32; 4]>::from(color); 6]);
+        if let Some(quad_pixels) = &mut self.quad_pixels {
+            quad_pixels.push(None);
+        }
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_mesh() -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new());
+        mesh
+    }
+
+    #[test]
+    fn remove_pixels_keeps_other_quads() {
+        let mut mesh = empty_mesh();
+        let mut quad_pixels = Vec::new();
+        let mut editor = MulticolorMeshEditor::new(&mut mesh);
+        editor.quad_pixels = Some(&mut quad_pixels);
+        let red = PixelColor::from([255, 0, 0, 255]);
+        let blue = PixelColor::from([0, 0, 255, 255]);
+        editor
+            .add_pixel(Pixel::new(0, 0, red))
+            .add_pixel(Pixel::new(1, 0, blue))
+            .add_small_pixel(Pixel::new(1, 0, red))
+            .add_pixel(Pixel::new(2, 0, red));
+
+        editor.remove_pixels(&HashSet::from_iter([UVec2::new(1, 0)]));
+        assert_eq!(editor.positions.len(), 3 * 6);
+        assert_eq!(editor.colors.len(), 3 * 6);
+        assert!(editor
+            .colors
+            .iter()
+            .all(|&color| color == <[f32; 4]>::from(red)));
+        assert_eq!(
+            quad_pixels,
+            [Some(UVec2::new(0, 0)), None, Some(UVec2::new(2, 0))]
+        );
+    }
+}
@@ -1,8 +1,12 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 
-use crate::art::{Art, Pixel};
+use crate::{
+    art::{Art, Pixel},
+    bit_grid::BitGrid,
+    grid::Connectivity,
+};
 
-mod lines;
+pub mod lines;
 mod orderings;
 
 const SPEED: f32 = 6.0;
@@ -25,11 +29,111 @@ pub enum OrderingType {
     Spiral = 2,
 }
 
+impl OrderingType {
+    pub const ALL: [Self; 3] = [Self::Default, Self::SideToSide, Self::Spiral];
+
+    /// The name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::SideToSide => "side-to-side",
+            Self::Spiral => "spiral",
+        }
+    }
+}
+
+/// Changes to an [`Ordering`] that are made before playing it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OrderingOptions {
+    pub reversed: bool,
+    /// Groups pixels of the same color together, in the order each color
+    /// first appears
+    pub by_color: bool,
+    /// Finishes each island of touching pixels before starting the next,
+    /// in the order each island is first reached
+    pub by_island: bool,
+}
+
+impl OrderingOptions {
+    fn apply(self, data: &mut [Pixel], art: &Art) {
+        if self.by_color {
+            let mut color_order = HashMap::new();
+            for pixel in data.iter() {
+                let next = color_order.len();
+                color_order.entry(pixel.color).or_insert(next);
+            }
+            data.sort_by_key(|pixel| color_order[&pixel.color]);
+        }
+        // After grouping by color, so each island keeps its colors grouped
+        if self.by_island {
+            let labels = art
+                .grid()
+                .components(Connectivity::Four, Option::is_some)
+                .labels;
+            let mut island_order = HashMap::new();
+            for pixel in data.iter() {
+                let next = island_order.len();
+                island_order.entry(labels[pixel.pos]).or_insert(next);
+            }
+            data.sort_by_key(|pixel| island_order[&labels[pixel.pos]]);
+        }
+        if self.reversed {
+            data.reverse();
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Ordering {
     data: Vec<Pixel>,
 }
 
+/// Measurements of a valid [`Ordering`]
+#[derive(Debug, Clone, Copy)]
+pub struct OrderingStats {
+    pub pixels: usize,
+    /// How many steps go to a pixel that doesn't touch the previous one
+    pub jumps: usize,
+    /// The furthest step, in pixels along either axis
+    pub longest_step: u32,
+}
+
+impl Ordering {
+    /// Checks that `self` visits every visible pixel of `art` exactly once,
+    /// with the right color
+    pub fn check(&self, art: &Art) -> Result<OrderingStats, String> {
+        let mut seen = BitGrid::new(art.size());
+        for pixel in &self.data {
+            let pos = pixel.pos;
+            if art.grid().get(pos) != Some(&Some(pixel.color)) {
+                return Err(format!("Pixel at {pos} doesn't match the art"));
+            }
+            if seen.get(pos) {
+                return Err(format!("Pixel at {pos} is visited twice"));
+            }
+            seen.set(pos, true);
+        }
+        let visible = art.grid().positions_where(Option::is_some).count();
+        if seen.count_ones() < visible {
+            return Err(format!(
+                "{} pixels are never visited",
+                visible - seen.count_ones()
+            ));
+        }
+
+        let steps = self.data.windows(2).map(|pair| {
+            (pair[1].pos.as_ivec2() - pair[0].pos.as_ivec2())
+                .abs()
+                .max_element() as u32
+        });
+        Ok(OrderingStats {
+            pixels: self.data.len(),
+            jumps: steps.clone().filter(|&step| step > 1).count(),
+            longest_step: steps.max().unwrap_or(0),
+        })
+    }
+}
+
 #[derive(Debug, Default, Resource)]
 pub struct Orderings([Option<Ordering>; 3]);
 
@@ -56,18 +160,76 @@ impl Orderings {
 
 #[derive(Debug, Resource)]
 pub struct CurrentOrdering {
+    typ: OrderingType,
+    options: OrderingOptions,
     ordering: Ordering,
     pos: usize,
 }
 
 impl CurrentOrdering {
-    pub fn init(orderings: &mut Orderings, typ: OrderingType, art: &Art) -> Self {
+    pub fn init(
+        orderings: &mut Orderings,
+        typ: OrderingType,
+        options: OrderingOptions,
+        art: &Art,
+    ) -> Self {
+        let mut ordering = orderings.get_or_generate(typ, art).clone();
+        options.apply(&mut ordering.data, art);
         Self {
-            ordering: orderings.get_or_generate(typ, art).clone(),
+            typ,
+            options,
+            ordering,
             pos: 0,
         }
     }
 
+    /// Like [`Self::init`], but only includes the pixels of `art` that
+    /// changed from `previous`
+    pub fn init_changed(
+        orderings: &mut Orderings,
+        typ: OrderingType,
+        options: OrderingOptions,
+        previous: &Art,
+        art: &Art,
+    ) -> Self {
+        let mut data: Vec<_> = orderings
+            .get_or_generate(typ, art)
+            .data
+            .iter()
+            .filter(|pixel| art.changed_from(previous, pixel.pos))
+            .copied()
+            .collect();
+        options.apply(&mut data, art);
+        Self {
+            typ,
+            options,
+            ordering: Ordering { data },
+            pos: 0,
+        }
+    }
+
+    pub fn typ(&self) -> OrderingType {
+        self.typ
+    }
+
+    pub fn options(&self) -> OrderingOptions {
+        self.options
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.ordering.data.len()
+    }
+
+    /// Every pixel, in order
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.ordering.data
+    }
+
+    /// The pixels that haven't been reached yet
+    pub fn remaining(&self) -> &[Pixel] {
+        &self.ordering.data[self.pos.min(self.ordering.data.len())..]
+    }
+
     pub fn peek(&self) -> Option<Pixel> {
         self.ordering.data.get(self.pos).copied()
     }
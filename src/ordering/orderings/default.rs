@@ -26,14 +26,11 @@ impl Plugin for DefaultPlugin {
 }
 
 pub fn generate_fast(art: &Art) -> Ordering {
-    let mut data = Vec::new();
-    for (row, y) in art.rows().zip(0..) {
-        for (&color, x) in row.iter().zip(0..) {
-            if let Some(color) = color {
-                data.push(Pixel::new(x, y, color));
-            }
-        }
-    }
+    let data = art
+        .grid()
+        .iter_with_pos()
+        .filter_map(|(pos, &color)| Some(Pixel { pos, color: color? }))
+        .collect();
     Ordering { data }
 }
 
@@ -141,7 +138,7 @@ fn enter_system(
 fn step_system(
     mut commands: Commands,
     mut dot_query: Query<(&mut Dot, &mut Transform)>,
-    mesh_query: Query<&MulticolorMesh>,
+    mut mesh_query: Query<&mut MulticolorMesh>,
     mut line_query: Query<&mut Line, With<DotLine>>,
     keys: Res<Input<KeyCode>>,
     time: Res<Time>,
@@ -175,7 +172,10 @@ fn step_system(
                 .spawn(&mut commands, -1.0)
                 .insert(DotLine);
             }
-            mesh_query.single().edit(&mut meshes).add_small_pixel(pixel);
+            mesh_query
+                .single_mut()
+                .edit(&mut meshes)
+                .add_small_pixel(pixel);
         }
         dot.update_position(&mut transform);
         if let Ok(mut line) = line_query.get_single_mut() {
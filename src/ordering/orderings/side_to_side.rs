@@ -1,5 +1,52 @@
-use crate::{art::Art, ordering::Ordering};
+use crate::{
+    art::{Art, Pixel},
+    ordering::Ordering,
+};
 
+/// Goes along each row, alternating left-to-right and right-to-left
 pub fn generate_fast(art: &Art) -> Ordering {
-    todo!()
+    let mut data = Vec::new();
+    for (row, y) in art.rows().zip(0..) {
+        let pixels = row
+            .iter()
+            .zip(0..art.width())
+            .filter_map(|(&color, x)| Some(Pixel::new(x, y, color?)));
+        if y % 2 == 0 {
+            data.extend(pixels);
+        } else {
+            data.extend(pixels.rev());
+        }
+    }
+    Ordering { data }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn snakes_along_rows() {
+        let color = Some([0, 0, 0, 255].into());
+        let mut grid = Grid::from_vec(vec![color; 9], 3);
+        grid[UVec2::new(1, 1)] = None;
+        let art = Art::from_grid(grid).unwrap();
+
+        let positions: Vec<_> = generate_fast(&art).data.iter().map(|p| p.pos).collect();
+        assert_eq!(
+            positions,
+            [
+                UVec2::new(0, 0),
+                UVec2::new(1, 0),
+                UVec2::new(2, 0),
+                UVec2::new(2, 1),
+                UVec2::new(0, 1),
+                UVec2::new(0, 2),
+                UVec2::new(1, 2),
+                UVec2::new(2, 2),
+            ]
+        );
+    }
 }
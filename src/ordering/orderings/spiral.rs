@@ -1,5 +1,89 @@
+use bevy::prelude::*;
+
 use crate::{art::Art, ordering::Ordering};
 
+/// Goes clockwise around the edge of the art, from the top left corner
+/// inwards
 pub fn generate_fast(art: &Art) -> Ordering {
-    todo!()
+    let mut data = Vec::new();
+    let mut visit = |x: u32, y: u32| data.extend(art.pixel(UVec2::new(x, y)));
+
+    let (mut min, mut max) = (IVec2::ZERO, art.size().as_ivec2() - 1);
+    while min.x <= max.x && min.y <= max.y {
+        let (min_u, max_u) = (min.as_uvec2(), max.as_uvec2());
+        for x in min_u.x..=max_u.x {
+            visit(x, min_u.y);
+        }
+        for y in min_u.y + 1..=max_u.y {
+            visit(max_u.x, y);
+        }
+        if min.y < max.y {
+            for x in (min_u.x..max_u.x).rev() {
+                visit(x, max_u.y);
+            }
+        }
+        if min.x < max.x {
+            for y in (min_u.y + 1..max_u.y).rev() {
+                visit(min_u.x, y);
+            }
+        }
+        min += 1;
+        max -= 1;
+    }
+    Ordering { data }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+
+    use super::*;
+    use crate::grid::Grid;
+
+    fn full_art(width: u32, height: u32) -> Art {
+        let color = Some([0, 0, 0, 255].into());
+        Art::from_grid(Grid::from_vec(
+            vec![color; (width * height) as usize],
+            width as usize,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn spirals_clockwise_inwards() {
+        let positions: Vec<_> = generate_fast(&full_art(4, 3))
+            .data
+            .iter()
+            .map(|p| (p.pos.x, p.pos.y))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (3, 1),
+                (3, 2),
+                (2, 2),
+                (1, 2),
+                (0, 2),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn visits_every_pixel_once() {
+        for width in 2..8 {
+            for height in 2..8 {
+                let ordering = generate_fast(&full_art(width, height));
+                let visited: HashSet<_> = ordering.data.iter().map(|p| p.pos).collect();
+                assert_eq!(ordering.data.len(), (width * height) as usize);
+                assert_eq!(visited.len(), ordering.data.len(), "{width}x{height}");
+            }
+        }
+    }
 }
@@ -0,0 +1,320 @@
+use std::{fs, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::art::{Art, ArtFrames, PixelColor};
+
+const KMEANS_ITERATIONS: usize = 8;
+
+#[derive(Debug, Clone, Resource)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<PixelColor>,
+}
+
+impl Palette {
+    pub fn load_from_path(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Unable to open file: {e}"))?;
+        let name = path.file_stem().map_or_else(
+            || "{unknown}".to_owned(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let palette = match path.extension().and_then(|e| e.to_str()) {
+            Some("gpl") => Self::parse_gpl(&text, name),
+            _ => Self::parse_hex(&text, name),
+        }?;
+        if palette.colors.is_empty() {
+            Err("Palette must have at least one color".to_owned())
+        } else {
+            Ok(palette)
+        }
+    }
+
+    /// Parses a GIMP palette
+    pub fn parse_gpl(text: &str, mut name: String) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some("GIMP Palette") {
+            return Err("Not a GIMP palette".to_owned());
+        }
+
+        let mut colors = Vec::new();
+        for (index, line) in lines {
+            let line = line.trim();
+            if let Some(palette_name) = line.strip_prefix("Name:") {
+                name = palette_name.trim().to_owned();
+            } else if !(line.is_empty() || line.starts_with('#') || line.starts_with("Columns:")) {
+                let mut channels = line.split_whitespace().map(str::parse::<u8>);
+                let mut channel = || {
+                    channels
+                        .next()
+                        .and_then(Result::ok)
+                        .ok_or_else(|| format!("Line {}: Invalid color", index + 1))
+                };
+                colors.push([channel()?, channel()?, channel()?, 255].into());
+            }
+        }
+        Ok(Self { name, colors })
+    }
+
+    /// Parses a Lospec hex palette, one `rrggbb` color per line
+    pub fn parse_hex(text: &str, name: String) -> Result<Self, String> {
+        let colors = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                PixelColor::from_hex(line.trim())
+                    .ok_or_else(|| format!("Line {}: Invalid color", index + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { name, colors })
+    }
+
+    /// Picks at most `count` colors that best represent `colors`, each
+    /// weighted by how many pixels use it.  Starts with a median cut, then
+    /// refines the colors with k-means
+    pub fn quantize(colors: &[(PixelColor, usize)], count: usize) -> Self {
+        let points: Vec<_> = colors
+            .iter()
+            .map(|&(color, pixels)| (color.oklab(), pixels as f32))
+            .collect();
+
+        let mut buckets = vec![points.clone()];
+        while buckets.len() < count {
+            let widest = buckets
+                .iter()
+                .enumerate()
+                .filter(|(_, bucket)| bucket.len() > 1)
+                .map(|(index, bucket)| {
+                    let (min, max) = bucket.iter().fold(
+                        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                        |(min, max), &(lab, _)| (min.min(lab), max.max(lab)),
+                    );
+                    (index, max - min)
+                })
+                .max_by(|(_, a), (_, b)| a.max_element().total_cmp(&b.max_element()));
+            let Some((index, extent)) = widest else {
+                break;
+            };
+
+            let axis = if extent.x >= extent.y && extent.x >= extent.z {
+                0
+            } else if extent.y >= extent.z {
+                1
+            } else {
+                2
+            };
+            let mut bucket = buckets.swap_remove(index);
+            bucket.sort_by(|(a, _), (b, _)| a[axis].total_cmp(&b[axis]));
+            let half_weight = bucket.iter().map(|&(_, weight)| weight).sum::<f32>() * 0.5;
+            let mut weight = 0.0;
+            let median = bucket
+                .iter()
+                .position(|&(_, w)| {
+                    weight += w;
+                    weight >= half_weight
+                })
+                .unwrap_or(0);
+            let upper = bucket.split_off((median + 1).min(bucket.len() - 1));
+            buckets.push(bucket);
+            buckets.push(upper);
+        }
+
+        let mut centers: Vec<_> = buckets.iter().map(|bucket| weighted_mean(bucket)).collect();
+        for _ in 0..KMEANS_ITERATIONS {
+            let mut clusters = vec![Vec::new(); centers.len()];
+            for &point in &points {
+                clusters[nearest_center(&centers, point.0)].push(point);
+            }
+            for (center, cluster) in centers.iter_mut().zip(&clusters) {
+                if !cluster.is_empty() {
+                    *center = weighted_mean(cluster);
+                }
+            }
+        }
+
+        Self {
+            name: format!("{} colors", centers.len()),
+            colors: centers.into_iter().map(PixelColor::from_oklab).collect(),
+        }
+    }
+
+    /// The index of the palette color that looks closest to `color`
+    pub fn nearest(&self, color: PixelColor) -> usize {
+        let lab = color.oklab();
+        self.colors
+            .iter()
+            .map(|palette_color| palette_color.oklab().distance_squared(lab))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap()
+            .0
+    }
+
+    /// Maps every color in `colors` to the nearest palette color
+    pub fn nearest_recolor(&self, colors: &[PixelColor]) -> Recolor {
+        Recolor {
+            palette: self.colors.clone(),
+            mapping: colors
+                .iter()
+                .map(|&color| (color, self.nearest(color)))
+                .collect(),
+        }
+    }
+}
+
+fn weighted_mean(points: &[(Vec3, f32)]) -> Vec3 {
+    let (sum, weight) = points
+        .iter()
+        .fold((Vec3::ZERO, 0.0), |(sum, weight), &(lab, w)| {
+            (sum + lab * w, weight + w)
+        });
+    sum / weight
+}
+
+fn nearest_center(centers: &[Vec3], lab: Vec3) -> usize {
+    centers
+        .iter()
+        .map(|center| center.distance_squared(lab))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap()
+        .0
+}
+
+/// How many colors art is reduced to, and whether that happens as soon as
+/// it's loaded
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct ColorReduction {
+    pub count: usize,
+    pub on_load: bool,
+}
+
+impl Default for ColorReduction {
+    fn default() -> Self {
+        Self {
+            count: 16,
+            on_load: false,
+        }
+    }
+}
+
+impl ColorReduction {
+    /// Reduces the colors of newly loaded `frames`, if [`Self::on_load`] is
+    /// set
+    pub fn apply_on_load(&self, frames: &mut ArtFrames) {
+        if self.on_load {
+            frames.reduce_colors(self.count);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Recolor {
+    palette: Vec<PixelColor>,
+    /// Each original color, and the index of the palette color it becomes
+    pub mapping: Vec<(PixelColor, usize)>,
+}
+
+impl Recolor {
+    pub fn palette(&self) -> &[PixelColor] {
+        &self.palette
+    }
+
+    /// Replaces the colors of `art`, keeping the alpha of each pixel
+    pub fn apply(&self, art: &Art) -> Art {
+        let mapping: HashMap<_, _> = self
+            .mapping
+            .iter()
+            .map(|&(from, index)| (from, self.palette[index]))
+            .collect();
+        art.map_colors(|color| match mapping.get(&color) {
+            Some(to) => {
+                let [r, g, b, _] = to.rgba();
+                [r, g, b, color.rgba()[3]].into()
+            }
+            None => color,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_gpl_skips_header_and_comments() {
+        let text = "GIMP Palette\nName: Sunset\nColumns: 4\n# a comment\n\n255   0  10\tRed\n  0 128 255 Sky blue\n";
+        let palette = Palette::parse_gpl(text, "file".to_owned()).unwrap();
+        assert_eq!(palette.name, "Sunset");
+        assert_eq!(
+            palette.colors,
+            vec![[255, 0, 10, 255].into(), [0, 128, 255, 255].into()]
+        );
+
+        assert!(Palette::parse_gpl("255 0 0\n", "file".to_owned()).is_err());
+        assert!(Palette::parse_gpl("GIMP Palette\n255 0\n", "file".to_owned()).is_err());
+    }
+
+    #[test]
+    fn parse_hex_rejects_bad_colors() {
+        let palette = Palette::parse_hex("ff0000\n\n00ff80\n", "hex".to_owned()).unwrap();
+        assert_eq!(
+            palette.colors,
+            vec![[255, 0, 0, 255].into(), [0, 255, 128, 255].into()]
+        );
+
+        assert_eq!(
+            Palette::parse_hex("ff0000\nzz0000\n", "hex".to_owned()).unwrap_err(),
+            "Line 2: Invalid color"
+        );
+        assert!(Palette::parse_hex("ff00\n", "hex".to_owned()).is_err());
+    }
+
+    #[test]
+    fn quantize_limits_colors() {
+        let colors: Vec<(PixelColor, usize)> = (0..8u8)
+            .map(|i| ([i * 30, 255 - i * 30, i * 10, 255].into(), 1 + i as usize))
+            .collect();
+        for count in 1..=4 {
+            assert!(Palette::quantize(&colors, count).colors.len() <= count);
+        }
+    }
+
+    #[test]
+    fn quantize_keeps_few_colors() {
+        let colors: Vec<(PixelColor, usize)> = vec![
+            ([255, 0, 0, 255].into(), 5),
+            ([0, 0, 255, 255].into(), 1),
+            ([20, 200, 40, 255].into(), 3),
+        ];
+        let palette = Palette::quantize(&colors, 4);
+        assert_eq!(palette.colors.len(), colors.len());
+        for (color, _) in colors {
+            assert!(palette.colors.contains(&color), "{color:?} was lost");
+        }
+    }
+
+    #[test]
+    fn nearest_compares_in_oklab() {
+        let palette = Palette {
+            name: "test".to_owned(),
+            colors: vec![
+                [0, 0, 0, 255].into(),
+                [255, 255, 255, 255].into(),
+                [255, 0, 0, 255].into(),
+            ],
+        };
+        assert_eq!(palette.nearest([10, 10, 10, 255].into()), 0);
+        assert_eq!(palette.nearest([240, 240, 250, 255].into()), 1);
+        assert_eq!(palette.nearest([200, 30, 20, 255].into()), 2);
+
+        // Closer to black than white in RGB, but lighter than mid-grey to the
+        // eye, which Oklab accounts for
+        let black_and_white = Palette {
+            colors: palette.colors[..2].to_vec(),
+            ..palette
+        };
+        assert_eq!(black_and_white.nearest([110, 110, 110, 255].into()), 1);
+    }
+}
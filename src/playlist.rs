@@ -0,0 +1,277 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use fastrand::Rng;
+
+use crate::art::{Art, ArtFrames, ArtName};
+
+#[derive(Debug, Resource)]
+pub struct Playlist {
+    entries: Vec<PlaylistEntry>,
+    /// The order the entries play in, as indices into `entries`
+    order: Vec<usize>,
+    position: usize,
+    /// Seeds the next shuffle, or `None` if the playlist isn't shuffled
+    shuffle_seed: Option<u64>,
+    /// Goes back to the start after the last entry
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistEntry {
+    pub name: String,
+    /// The file the art was loaded from, if any
+    pub path: Option<PathBuf>,
+    pub frames: ArtFrames,
+}
+
+impl Playlist {
+    /// Creates a playlist, or `None` if there are no entries
+    pub fn new(entries: Vec<PlaylistEntry>) -> Option<Self> {
+        (!entries.is_empty()).then(|| Self {
+            order: (0..entries.len()).collect(),
+            entries,
+            position: 0,
+            shuffle_seed: None,
+            looping: false,
+        })
+    }
+
+    /// Creates a playlist from the tiles of a sprite sheet, or `None` if
+    /// there are no tiles
+    pub fn from_tiles(sheet_name: &str, tiles: Vec<(usize, Art)>) -> Option<Self> {
+        Self::new(
+            tiles
+                .into_iter()
+                .map(|(index, art)| PlaylistEntry {
+                    name: format!("{sheet_name} [tile {index}]"),
+                    path: None,
+                    frames: ArtFrames::from(art),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// How far through the playlist the current entry is
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn current(&self) -> &PlaylistEntry {
+        &self.entries[self.order[self.position]]
+    }
+
+    /// Replaces the frames of the current entry, such as after its file
+    /// is reloaded
+    pub fn set_current_frames(&mut self, frames: ArtFrames) {
+        let index = self.order[self.position];
+        self.entries[index].frames = frames;
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle_seed.is_some()
+    }
+
+    /// Shuffles the entries after the current one with `seed`, or puts
+    /// them back in order if it's `None`
+    pub fn set_shuffle(&mut self, seed: Option<u64>) {
+        let current = self.order[self.position];
+        self.shuffle_seed = seed;
+        self.order = (0..self.len()).collect();
+        if seed.is_some() {
+            self.order.swap(0, current);
+            self.shuffle_from(1);
+            self.position = 0;
+        } else {
+            self.position = current;
+        }
+    }
+
+    /// Shuffles every entry with `seed` and goes back to the start, for
+    /// playlists that haven't started yet
+    pub fn shuffle_all(&mut self, seed: u64) {
+        self.shuffle_seed = Some(seed);
+        self.order = (0..self.len()).collect();
+        self.position = 0;
+        self.shuffle_from(0);
+    }
+
+    fn shuffle_from(&mut self, start: usize) {
+        if let Some(seed) = self.shuffle_seed {
+            let rng = Rng::with_seed(seed);
+            rng.shuffle(&mut self.order[start..]);
+            self.shuffle_seed = Some(rng.u64(..));
+        }
+    }
+
+    /// Returns true if [`Self::advance`] won't have to wrap around to the
+    /// start, or it's allowed to
+    pub fn has_next(&self) -> bool {
+        self.looping || self.position + 1 < self.len()
+    }
+
+    /// Moves to the next entry, wrapping around to the start after the
+    /// last one.  Shuffled playlists are shuffled again when they wrap
+    pub fn advance(&mut self) -> &PlaylistEntry {
+        self.position += 1;
+        if self.position == self.len() {
+            self.position = 0;
+            self.shuffle_from(0);
+        }
+        self.current()
+    }
+}
+
+/// Loads the art named by each command line argument, like [`load_all`].
+/// Returns what loaded, and an error for each file that didn't
+pub fn load_args(args: impl IntoIterator<Item = String>) -> (Vec<PlaylistEntry>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (name, entry) in load_all(args) {
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(err) => errors.push(format!("{name}: {err}")),
+        }
+    }
+    (entries, errors)
+}
+
+/// Loads the art named by each command line argument: `-` reads stdin,
+/// and a directory loads every file inside it.  Returns the name of each
+/// file along with how it loaded
+pub fn load_all(
+    args: impl IntoIterator<Item = String>,
+) -> Vec<(String, Result<PlaylistEntry, String>)> {
+    let mut loaded = Vec::new();
+    let mut load = |name: String, path: Option<&Path>, frames: Result<ArtFrames, String>| {
+        let entry = frames.map(|frames| PlaylistEntry {
+            name: name.clone(),
+            path: path.map(Path::to_owned),
+            frames,
+        });
+        loaded.push((name, entry));
+    };
+
+    for arg in args {
+        if arg == "-" {
+            load("stdin".to_owned(), None, ArtFrames::load_from_stdin());
+            continue;
+        }
+
+        let path = Path::new(&arg);
+        if !path.is_dir() {
+            load(
+                ArtName::from_path(path).0,
+                Some(path),
+                ArtFrames::load_from_path(path),
+            );
+            continue;
+        }
+
+        let mut paths = match fs::read_dir(path) {
+            Ok(dir) => dir
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                load(arg, None, Err(format!("Unable to read directory: {err}")));
+                continue;
+            }
+        };
+        paths.sort();
+        for path in paths {
+            load(
+                ArtName::from_path(&path).0,
+                Some(&path),
+                ArtFrames::load_from_path(&path),
+            );
+        }
+    }
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+
+    use super::*;
+    use crate::grid::Grid;
+
+    fn playlist(len: usize) -> Playlist {
+        let art = Art::from_grid(Grid::from_vec(vec![Some([0, 0, 0, 255].into()); 4], 2)).unwrap();
+        Playlist::from_tiles(
+            "sheet",
+            (0..len).map(|index| (index, art.clone())).collect(),
+        )
+        .unwrap()
+    }
+
+    /// The names of the next `count` entries, starting with the current one
+    fn play(playlist: &mut Playlist, count: usize) -> Vec<String> {
+        let mut names = vec![playlist.current().name.clone()];
+        for _ in 1..count {
+            names.push(playlist.advance().name.clone());
+        }
+        names
+    }
+
+    #[test]
+    fn stops_at_end_unless_looping() {
+        let mut playlist = playlist(3);
+        playlist.advance();
+        assert!(playlist.has_next());
+        playlist.advance();
+        assert_eq!(playlist.position(), 2);
+        assert!(!playlist.has_next());
+
+        playlist.looping = true;
+        assert!(playlist.has_next());
+        assert_eq!(playlist.advance().name, "sheet [tile 0]");
+        assert_eq!(playlist.position(), 0);
+    }
+
+    #[test]
+    fn shuffle_plays_everything_each_cycle() {
+        let mut playlist = playlist(5);
+        playlist.looping = true;
+        playlist.shuffle_all(42);
+        let names = play(&mut playlist, 15);
+        for cycle in names.chunks(5) {
+            assert_eq!(cycle.iter().collect::<HashSet<_>>().len(), 5, "{names:?}");
+        }
+
+        let mut again = self::playlist(5);
+        again.shuffle_all(42);
+        assert_eq!(play(&mut again, 5), names[..5]);
+    }
+
+    #[test]
+    fn set_shuffle_keeps_current_entry() {
+        let mut playlist = playlist(5);
+        playlist.advance();
+        playlist.advance();
+        let current = playlist.current().name.clone();
+
+        playlist.set_shuffle(Some(7));
+        assert!(playlist.is_shuffled());
+        assert_eq!(playlist.current().name, current);
+        assert_eq!(playlist.position(), 0);
+        assert_eq!(
+            play(&mut playlist, 5).iter().collect::<HashSet<_>>().len(),
+            5
+        );
+
+        playlist.advance();
+        let current = playlist.current().name.clone();
+        playlist.set_shuffle(None);
+        assert!(!playlist.is_shuffled());
+        assert_eq!(playlist.current().name, current);
+    }
+}
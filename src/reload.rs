@@ -0,0 +1,105 @@
+//! Reloads the art when its file changes, so it can be edited in another
+//! program while playing
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    art::{ArtFrames, ArtName, ArtPath},
+    camera::AreaTrackingProjection,
+    ordering::{CurrentOrdering, Orderings},
+    playlist::Playlist,
+    status::Status,
+    GameState,
+};
+
+/// How often the file is checked for changes, in seconds
+const CHECK_INTERVAL: f32 = 0.5;
+
+pub struct ReloadPlugin;
+
+impl Plugin for ReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(reload_system.run_if_resource_exists::<ArtPath>());
+    }
+}
+
+struct CheckTimer(Timer);
+
+impl Default for CheckTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(CHECK_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+/// Reloads the art, then restarts the current game or generation with it
+#[allow(clippy::too_many_arguments)]
+fn reload_system(
+    mut commands: Commands,
+    state: Res<CurrentState<GameState>>,
+    time: Res<Time>,
+    mut timer: Local<CheckTimer>,
+    mut art_path: ResMut<ArtPath>,
+    frames: Option<Res<ArtFrames>>,
+    mut orderings: ResMut<Orderings>,
+    current_ordering: Option<Res<CurrentOrdering>>,
+    playlist: Option<ResMut<Playlist>>,
+    mut projection_query: Query<&mut AreaTrackingProjection>,
+) {
+    // The editor and gallery don't show the art, and shouldn't be left
+    if !matches!(
+        state.0,
+        GameState::MainMenu | GameState::Generate(_) | GameState::Play(_)
+    ) || !timer.0.tick(time.delta()).just_finished()
+        || !art_path.changed()
+    {
+        return;
+    }
+
+    let mut new_frames = match ArtFrames::load_from_path(&art_path.path) {
+        Ok(frames) => frames,
+        Err(err) => {
+            commands.insert_resource(ArtName(err));
+            return;
+        }
+    };
+    // Keeps the recoloring, layers and transforms.  Colors new to the art
+    // are left as they are
+    if let Some(frames) = frames {
+        if let Err(err) = new_frames.keep_settings(&frames) {
+            commands.insert_resource(Status::new(err));
+            return;
+        }
+    }
+    if let Some(mut playlist) = playlist {
+        if playlist.current().path.as_ref() == Some(&art_path.path) {
+            playlist.set_current_frames(new_frames.clone());
+        }
+    }
+    let art = new_frames.current();
+    orderings.clear();
+
+    match state.0 {
+        GameState::Play(_) => {
+            if let Some(current_ordering) = current_ordering {
+                commands.insert_resource(CurrentOrdering::init(
+                    &mut orderings,
+                    current_ordering.typ(),
+                    current_ordering.options(),
+                    &art,
+                ));
+            }
+            projection_query.single_mut().track_art(&art);
+            commands.insert_resource(NextState(state.0));
+        }
+        GameState::Generate(_) => {
+            projection_query.single_mut().track_art(&art);
+            commands.insert_resource(NextState(state.0));
+        }
+        _ => {}
+    }
+
+    commands.insert_resource(art);
+    commands.insert_resource(new_frames);
+    commands.insert_resource(ArtName::from_path(&art_path.path));
+}
@@ -0,0 +1,57 @@
+//! Short messages for the user, like where something was saved or why it
+//! couldn't be loaded, kept apart from the name of the art
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, RichText},
+    EguiContext,
+};
+
+const DURATION: Duration = Duration::from_secs(4);
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(show_status_system);
+    }
+}
+
+/// Shown at the bottom of the screen for a few seconds
+#[derive(Debug, Resource)]
+pub struct Status {
+    message: String,
+    timer: Timer,
+}
+
+impl Status {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            timer: Timer::new(DURATION, TimerMode::Once),
+        }
+    }
+}
+
+fn show_status_system(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    status: Option<ResMut<Status>>,
+    time: Res<Time>,
+) {
+    let Some(mut status) = status else {
+        return;
+    };
+    if status.timer.tick(time.delta()).finished() {
+        commands.remove_resource::<Status>();
+        return;
+    }
+    egui::TopBottomPanel::bottom("status")
+        .show_separator_line(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(RichText::new(&status.message).size(20.0));
+        });
+}
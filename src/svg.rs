@@ -0,0 +1,247 @@
+//! Vector versions of the art, with one `<rect>` for each rectangle of a
+//! single color
+
+use std::{fmt::Write, fs};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    art::{Art, PixelColor},
+    grid::Grid,
+    ordering::CurrentOrdering,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorRect {
+    pub pos: UVec2,
+    pub size: UVec2,
+    pub color: PixelColor,
+}
+
+/// Covers the visible pixels of `art` with as few rectangles as it can
+/// find, going along the rows and making each rectangle as wide and then as
+/// tall as it can
+pub fn greedy_rects(art: &Grid<Option<PixelColor>>) -> Vec<ColorRect> {
+    let mut covered = Grid::<bool>::new(art.size());
+    let mut rects = Vec::new();
+    for y in 0..art.height() {
+        for x in 0..art.width() {
+            let pos = UVec2::new(x, y);
+            let color = match art[pos] {
+                Some(color) if !covered[pos] => color,
+                _ => continue,
+            };
+            let fits = |pos: UVec2| art[pos] == Some(color) && !covered[pos];
+
+            let width = (x..art.width())
+                .take_while(|&x| fits(UVec2::new(x, y)))
+                .count() as u32;
+            let height = (y..art.height())
+                .take_while(|&y| (x..x + width).all(|x| fits(UVec2::new(x, y))))
+                .count() as u32;
+
+            for y in y..y + height {
+                for x in x..x + width {
+                    covered[UVec2::new(x, y)] = true;
+                }
+            }
+            rects.push(ColorRect {
+                pos,
+                size: UVec2::new(width, height),
+                color,
+            });
+        }
+    }
+    rects
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgSettings {
+    /// Reveals the rectangles in the order of the ordering when the SVG is
+    /// opened in a browser
+    pub animate: bool,
+    /// How long each pixel of the ordering takes to reveal, in milliseconds
+    pub pixel_delay: u32,
+    pub path: String,
+}
+
+impl Default for SvgSettings {
+    fn default() -> Self {
+        Self {
+            animate: false,
+            pixel_delay: 20,
+            path: "pixel_art.svg".to_owned(),
+        }
+    }
+}
+
+impl SvgSettings {
+    /// Writes the pixels of `art` as an SVG `scale` times its size in
+    /// pixels.  The rectangles are revealed when `ordering` first reaches one
+    /// of their pixels, if [`Self::animate`] is set
+    pub fn to_svg(
+        &self,
+        art: &Grid<Option<PixelColor>>,
+        ordering: Option<&CurrentOrdering>,
+        scale: u32,
+    ) -> String {
+        let mut rects: Vec<_> = greedy_rects(art)
+            .into_iter()
+            .map(|rect| (None, rect))
+            .collect();
+        if let (true, Some(ordering)) = (self.animate, ordering) {
+            let indices: HashMap<UVec2, usize> = ordering
+                .pixels()
+                .iter()
+                .enumerate()
+                .map(|(index, pixel)| (pixel.pos, index))
+                .collect();
+            for (start, rect) in &mut rects {
+                *start = (rect.pos.y..rect.pos.y + rect.size.y)
+                    .flat_map(|y| (rect.pos.x..rect.pos.x + rect.size.x).map(move |x| (x, y)))
+                    .filter_map(|(x, y)| indices.get(&UVec2::new(x, y)).copied())
+                    .min();
+            }
+            rects.sort_by_key(|(start, _)| *start);
+        }
+
+        let size = art.size() * scale.max(1);
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            size.x,
+            size.y,
+            art.width(),
+            art.height(),
+        );
+        svg.push('\n');
+        if self.animate && ordering.is_some() {
+            svg.push_str(
+                "<style>rect{opacity:0;animation:reveal 0s forwards}\
+                 @keyframes reveal{to{opacity:1}}</style>\n",
+            );
+        }
+        for (start, rect) in rects {
+            let [r, g, b, a] = rect.color.rgba();
+            write!(
+                svg,
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#{r:02x}{g:02x}{b:02x}""##,
+                rect.pos.x, rect.pos.y, rect.size.x, rect.size.y,
+            )
+            .unwrap();
+            if a < u8::MAX {
+                write!(svg, r#" fill-opacity="{:.3}""#, a as f32 / 255.0).unwrap();
+            }
+            if let Some(start) = start {
+                write!(
+                    svg,
+                    r#" style="animation-delay:{}ms""#,
+                    start as u64 * self.pixel_delay as u64
+                )
+                .unwrap();
+            }
+            svg.push_str("/>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Saves to [`Self::path`], returning a message for the user
+    pub fn save(
+        &self,
+        art: &Art,
+        ordering: Option<&CurrentOrdering>,
+        scale: u32,
+    ) -> Result<String, String> {
+        fs::write(&self.path, self.to_svg(art.grid(), ordering, scale))
+            .map_err(|e| format!("Unable to save SVG: {e}"))?;
+        Ok(format!("Saved {}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ordering::{OrderingOptions, OrderingType, Orderings};
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+    /// A red square, then blue above green
+    fn art() -> Art {
+        let data = [RED, RED, BLUE, RED, RED, GREEN]
+            .into_iter()
+            .map(|color| Some(color.into()))
+            .collect();
+        Art::from_grid(Grid::from_vec(data, 3)).unwrap()
+    }
+
+    #[test]
+    fn merges_same_color() {
+        assert_eq!(
+            greedy_rects(art().grid()),
+            [
+                ColorRect {
+                    pos: UVec2::ZERO,
+                    size: UVec2::splat(2),
+                    color: RED.into(),
+                },
+                ColorRect {
+                    pos: UVec2::new(2, 0),
+                    size: UVec2::ONE,
+                    color: BLUE.into(),
+                },
+                ColorRect {
+                    pos: UVec2::new(2, 1),
+                    size: UVec2::ONE,
+                    color: GREEN.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn animates_in_ordering_order() {
+        let art = art();
+        let ordering = CurrentOrdering::init(
+            &mut Orderings::default(),
+            OrderingType::SideToSide,
+            OrderingOptions {
+                reversed: true,
+                ..default()
+            },
+            &art,
+        );
+        let settings = SvgSettings {
+            animate: true,
+            pixel_delay: 10,
+            ..default()
+        };
+        let svg = settings.to_svg(art.grid(), Some(&ordering), 1);
+        let rects: Vec<_> = svg
+            .lines()
+            .filter(|line| line.starts_with("<rect"))
+            .collect();
+        assert_eq!(
+            rects,
+            [
+                r##"<rect x="0" y="0" width="2" height="2" fill="#ff0000" style="animation-delay:0ms"/>"##,
+                r##"<rect x="2" y="1" width="1" height="1" fill="#00ff00" style="animation-delay:20ms"/>"##,
+                r##"<rect x="2" y="0" width="1" height="1" fill="#0000ff" style="animation-delay:30ms"/>"##,
+            ]
+        );
+
+        let still = SvgSettings::default().to_svg(art.grid(), Some(&ordering), 1);
+        assert!(!still.contains("animation"));
+    }
+
+    #[test]
+    fn empty_art_is_valid_svg() {
+        let svg = SvgSettings::default().to_svg(&Grid::new(UVec2::new(3, 2)), None, 4);
+        assert_eq!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"12\" height=\"8\" \
+             viewBox=\"0 0 3 2\" shape-rendering=\"crispEdges\">\n</svg>\n"
+        );
+    }
+}
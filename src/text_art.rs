@@ -0,0 +1,151 @@
+//! A plain text format for art, made of a palette followed by a grid
+//! of palette characters, separated by a blank line:
+//!
+//! ```text
+//! r #ff0000
+//! b #0000ffcc
+//!
+//! .rr.
+//! rbbr
+//! ```
+//!
+//! `.` is always transparent.  Lines starting with `;` are comments
+
+use std::{fs, path::Path};
+
+use bevy::utils::HashMap;
+
+use crate::{
+    art::{Art, PixelColor},
+    grid::Grid,
+};
+
+const TRANSPARENT: char = '.';
+const COMMENT: char = ';';
+
+impl Art {
+    pub fn load_text_from_path(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Unable to open file: {e}"))?;
+        Self::parse_text(&text)
+    }
+
+    pub fn parse_text(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim_end()))
+            .filter(|(_, line)| !line.starts_with(COMMENT));
+
+        let mut palette = HashMap::new();
+        for (line_number, line) in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let mut chars = line.chars();
+            let key = chars.next().unwrap();
+            if key == TRANSPARENT {
+                return Err(format!(
+                    "Line {line_number}: `{TRANSPARENT}` is always transparent"
+                ));
+            }
+            let color = PixelColor::from_hex(chars.as_str().trim())
+                .ok_or_else(|| format!("Line {line_number}: Invalid color"))?;
+            palette.insert(key, color);
+        }
+
+        let mut width = None;
+        let mut data = Vec::new();
+        for (line_number, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let row_start = data.len();
+            for c in line.chars() {
+                data.push(match c {
+                    TRANSPARENT => None,
+                    c => Some(*palette.get(&c).ok_or_else(|| {
+                        format!("Line {line_number}: `{c}` is not in the palette")
+                    })?),
+                });
+            }
+            let row_width = data.len() - row_start;
+            if *width.get_or_insert(row_width) != row_width {
+                return Err(format!(
+                    "Line {line_number}: Rows must all be the same width"
+                ));
+            }
+        }
+
+        match width {
+            Some(width) if width > 0 => Self::from_grid(Grid::from_vec(data, width)),
+            _ => Err("Image must be at least 2x2".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parses_legend_and_grid() {
+        let art = Art::parse_text("r #ff0000\nb #0000ffcc\n\n.rr.\nrbbr\n").unwrap();
+        assert_eq!(art.size(), UVec2::new(4, 2));
+        let red = PixelColor::from([255, 0, 0, 255]);
+        let blue = PixelColor::from([0, 0, 255, 0xcc]);
+        assert_eq!(art[UVec2::new(1, 0)], Some(red));
+        assert_eq!(art[UVec2::new(0, 1)], Some(red));
+        assert_eq!(art[UVec2::new(1, 1)], Some(blue));
+    }
+
+    #[test]
+    fn dots_are_transparent() {
+        let art = Art::parse_text("r #ff0000\n\nr.\n.r").unwrap();
+        assert_eq!(art[UVec2::new(1, 0)], None);
+        assert_eq!(art[UVec2::new(0, 1)], None);
+        assert!(art[UVec2::new(1, 1)].is_some());
+    }
+
+    #[test]
+    fn skips_comments_and_trailing_whitespace() {
+        let art = Art::parse_text("; palette\nr #ff0000\n\n; art\nrr  \nrr\n\n").unwrap();
+        assert_eq!(art.size(), UVec2::new(2, 2));
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        assert_eq!(
+            Art::parse_text("r #ff0000\n\nrr\nr\n").unwrap_err(),
+            "Line 4: Rows must all be the same width"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_characters() {
+        assert_eq!(
+            Art::parse_text("r #ff0000\n\nrr\nrx\n").unwrap_err(),
+            "Line 4: `x` is not in the palette"
+        );
+    }
+
+    #[test]
+    fn rejects_transparent_key() {
+        assert_eq!(
+            Art::parse_text(". #ff0000\n\n..\n..\n").unwrap_err(),
+            "Line 1: `.` is always transparent"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        assert_eq!(
+            Art::parse_text("r #ff00\n\nrr\nrr\n").unwrap_err(),
+            "Line 1: Invalid color"
+        );
+    }
+
+    #[test]
+    fn rejects_art_that_is_too_small() {
+        assert!(Art::parse_text("r #ff0000\n\n").is_err());
+        assert!(Art::parse_text("r #ff0000\n\nrr\n").is_err());
+    }
+}
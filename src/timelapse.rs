@@ -0,0 +1,180 @@
+//! Timelapses of an ordering drawing the art, saved as an animated GIF or a
+//! folder of numbered PNGs
+
+use std::{fs, iter, path::Path};
+
+use bevy::prelude::*;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+
+use crate::{
+    art::Art,
+    export::ExportSettings,
+    menu::Selection,
+    ordering::{CurrentOrdering, Orderings},
+    playlist,
+};
+
+#[derive(Debug, Clone)]
+pub struct Timelapse {
+    /// How many pixels of the ordering are drawn between frames
+    pub pixels_per_frame: usize,
+    /// How long each frame shows, in milliseconds
+    pub delay: u32,
+    /// How long the finished art shows at the end, in milliseconds
+    pub hold: u32,
+    /// A `.gif` file, or else a folder for the PNGs
+    pub path: String,
+}
+
+impl Default for Timelapse {
+    fn default() -> Self {
+        Self {
+            pixels_per_frame: 4,
+            delay: 40,
+            hold: 2000,
+            path: "timelapse.gif".to_owned(),
+        }
+    }
+}
+
+impl Timelapse {
+    /// Draws `ordering` from the start, from the empty art to the finished
+    /// art.  Frames are drawn as they're taken, so only one is kept at a
+    /// time
+    pub fn frames<'a>(
+        &self,
+        art: &Art,
+        mut ordering: CurrentOrdering,
+        export: &'a ExportSettings,
+        clear_color: Color,
+    ) -> impl Iterator<Item = RgbaImage> + 'a {
+        let pixels_per_frame = self.pixels_per_frame.max(1);
+        let mut image = export.render(art, Some(&ordering), clear_color);
+        let mut started = false;
+        iter::from_fn(move || {
+            if started {
+                if ordering.is_finished() {
+                    return None;
+                }
+                for pixel in (0..pixels_per_frame).map_while(|_| ordering.next()) {
+                    export.draw_pixel(&mut image, pixel.pos, Some(pixel.color), clear_color);
+                }
+            }
+            started = true;
+            Some(image.clone())
+        })
+    }
+
+    /// Makes the timelapse and saves it to [`Self::path`], returning a
+    /// message for the user
+    pub fn save(
+        &self,
+        art: &Art,
+        ordering: CurrentOrdering,
+        export: &ExportSettings,
+        clear_color: Color,
+    ) -> Result<String, String> {
+        let frames = self.frames(art, ordering, export, clear_color);
+        let path = Path::new(&self.path);
+        let count = if path.extension().and_then(|e| e.to_str()) == Some("gif") {
+            self.save_gif(path, frames)
+        } else {
+            self.save_pngs(path, frames)
+        }
+        .map_err(|e| format!("Unable to save timelapse: {e}"))?;
+        Ok(format!("Saved {count} frames to {}", self.path))
+    }
+
+    /// Returns how many frames were saved
+    fn save_gif(
+        &self,
+        path: &Path,
+        frames: impl Iterator<Item = RgbaImage>,
+    ) -> image::ImageResult<usize> {
+        let mut encoder = GifEncoder::new(fs::File::create(path)?);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let mut frames = frames.peekable();
+        let mut count = 0;
+        while let Some(image) = frames.next() {
+            let delay = if frames.peek().is_none() {
+                self.hold.max(self.delay)
+            } else {
+                self.delay
+            };
+            encoder.encode_frame(Frame::from_parts(
+                image,
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay, 1),
+            ))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// PNGs have no delays, so the last frame is saved again for the hold.
+    /// Returns how many frames were saved
+    fn save_pngs(
+        &self,
+        folder: &Path,
+        frames: impl Iterator<Item = RgbaImage>,
+    ) -> image::ImageResult<usize> {
+        fs::create_dir_all(folder)?;
+        let frame_path = |index: usize| folder.join(format!("{index:05}.png"));
+        let mut last = None;
+        let mut count = 0;
+        for image in frames {
+            image.save(frame_path(count))?;
+            last = Some(image);
+            count += 1;
+        }
+        if let Some(last) = last {
+            let repeats = (self.hold / self.delay.max(1)).saturating_sub(1);
+            for _ in 0..repeats {
+                last.save(frame_path(count))?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Saves a timelapse of the art in `args`, printing the result.  Returns
+/// false if it failed
+pub fn run(
+    args: Vec<String>,
+    selection: Selection,
+    timelapse: &Timelapse,
+    export: &ExportSettings,
+    clear_color: Color,
+) -> bool {
+    let mut loaded = playlist::load_all(args);
+    if loaded.len() != 1 {
+        eprintln!("`timelapse` needs exactly one art, not {}", loaded.len());
+        return false;
+    }
+    let (name, entry) = loaded.remove(0);
+    let result = entry.and_then(|entry| {
+        let art = entry.frames.current();
+        let ordering = CurrentOrdering::init(
+            &mut Orderings::default(),
+            selection.ordering,
+            selection.options,
+            &art,
+        );
+        timelapse.save(&art, ordering, export, clear_color)
+    });
+    match result {
+        Ok(message) => {
+            println!("{message}");
+            true
+        }
+        Err(err) => {
+            eprintln!("{name}: {err}");
+            false
+        }
+    }
+}